@@ -16,6 +16,32 @@ class LauncherWindow extends StatefulWidget {
 
 class _LauncherWindowState extends State<LauncherWindow> {
 	LauncherPage _currentPage = LauncherPage.library;
+	void Function()? _stopListeningForSettings;
+
+	@override
+	void initState() {
+		super.initState();
+		_listenForSettings();
+	}
+
+	@override
+	void dispose() {
+		_stopListeningForSettings?.call();
+		super.dispose();
+	}
+
+	/// The tray's Settings entry opens the settings page.
+	Future<void> _listenForSettings() async {
+		try {
+			_stopListeningForSettings = await tauriListen("open_settings", (_) {
+				if (mounted) {
+					setState(() => _currentPage = LauncherPage.settings);
+				}
+			});
+		} catch (e) {
+			logger.add("[launcher_window.dart] Failed to listen for the tray: $e", level: LogLevel.Error);
+		}
+	}
 
 	@override
 	Widget build(BuildContext context) {
@@ -2,6 +2,7 @@ import 'package:flutter/material.dart';
 import 'screens/output_screen.dart';
 import 'launcher_window.dart';
 import 'screens/settings_screen.dart';
+import 'script_dialogs.dart';
 
 void main() async {
   runApp(const App());
@@ -37,6 +38,8 @@ class _AppState extends State<App> {
     if (settings.isDevMode) {
       logger.add("[main.dart] Successfully opened");
     }
+
+    listenForScriptDialogs();
   }
 
   @override
@@ -50,6 +53,7 @@ class _AppState extends State<App> {
     }
 
     return MaterialApp(
+        navigatorKey: navigatorKey,
         title: 'Game Launcher',
         debugShowCheckedModeBanner: false,
         theme: ThemeData(
@@ -23,6 +23,8 @@ class _LibraryScreenState extends State<LibraryScreen> {
 	List<GameInfo> _games = [];
 	bool _loading = true;
 	String? _selectedGame;
+	Map<String, Map<String, dynamic>> _runs = {};
+	void Function()? _stopListeningForRuns;
 
 	bool get isTauriAvailable {
 		final tauri = js.context['__TAURI__'];
@@ -33,6 +35,51 @@ class _LibraryScreenState extends State<LibraryScreen> {
 	Future<void> initState() async {
 		super.initState();
 		_init();
+		_listenForRuns();
+	}
+
+	@override
+	void dispose() {
+		_stopListeningForRuns?.call();
+		super.dispose();
+	}
+
+	/// Keeps track of running scripts, so their tiles can show what the script says it's doing.
+	Future<void> _listenForRuns() async {
+		try {
+			_stopListeningForRuns = await tauriListen("runs", (payload) => _setRuns(payload as List));
+			_setRuns(await tauriInvoke("get_runs") as List);
+		} catch (e) {
+			logger.add("[library.dart] Failed to listen for running scripts: $e", level: LogLevel.Error);
+		}
+	}
+
+	void _setRuns(List runs) {
+		if (!mounted) {
+			return;
+		}
+
+		setState(() {
+			_runs = {
+				for (final run in runs) run['game'] as String: Map<String, dynamic>.from(run as Map),
+			};
+		});
+	}
+
+	Widget _buildRunStatus(Map<String, dynamic> run) {
+		final progress = (run['progress'] as num?)?.toDouble();
+		final status = run['status'] as String? ?? "Running";
+		final text = progress == null ? status : "$status ${(progress * 100).round()}%";
+
+		return Column(
+			crossAxisAlignment: CrossAxisAlignment.start,
+			mainAxisSize: MainAxisSize.min,
+			children: [
+				Text(text, style: TextStyle(color: settings.oldDarkMode ? Colors.white70 : Colors.black54)),
+				const SizedBox(height: 4),
+				LinearProgressIndicator(value: progress),
+			],
+		);
 	}
 
 	
@@ -146,7 +193,17 @@ class _LibraryScreenState extends State<LibraryScreen> {
 								}
 							}
 
-							await tauriInvoke("make_plugin", {"name": name, "path": path, "code": code});
+							try {
+								await tauriInvoke("make_plugin", {"name": name, "path": path, "code": code});
+							} catch (e) {
+								if (mounted) {
+									ScaffoldMessenger.of(context).showSnackBar(
+										SnackBar(content: Text('$e')),
+									);
+								}
+
+								return;
+							}
 
 							_loadGames();
 						},
@@ -157,6 +214,73 @@ class _LibraryScreenState extends State<LibraryScreen> {
         );
 	}
 
+	Future<void> _onImportGames() async {
+		final candidates = await tauriInvoke("scan_library_sources") as List;
+
+		if (!mounted) return;
+
+		if (candidates.isEmpty) {
+			ScaffoldMessenger.of(context).showSnackBar(
+				SnackBar(content: Text('No new games found.')),
+			);
+
+			return;
+		}
+
+		final selected = List<bool>.filled(candidates.length, true);
+
+		showDialog(
+			context: context,
+			builder: (context) => StatefulBuilder(
+				builder: (context, setDialogState) => AlertDialog(
+					title: Text('Import Games'),
+					content: SizedBox(
+						width: 400,
+						child: ListView.builder(
+							shrinkWrap: true,
+							itemCount: candidates.length,
+							itemBuilder: (context, index) {
+								final game = candidates[index];
+
+								return CheckboxListTile(
+									value: selected[index],
+									title: Text(game['name']),
+									subtitle: Text("${game['source']}: ${game['command']}"),
+									onChanged: (value) => setDialogState(() => selected[index] = value ?? false),
+								);
+							},
+						),
+					),
+					actions: [
+						TextButton(
+							onPressed: () => Navigator.pop(context),
+							child: Text('Cancel'),
+						),
+						TextButton(
+							onPressed: () async {
+								Navigator.pop(context);
+
+								final games = [
+									for (var i = 0; i < candidates.length; i++)
+										if (selected[i]) candidates[i]
+								];
+
+								String res = await tauriInvoke("import_games", {"games": games});
+
+								if (settings.isDevMode) {
+									logger.add("[library.dart] $res");
+								}
+
+								_loadGames();
+							},
+							child: Text('Import'),
+						),
+					],
+				),
+			),
+		);
+	}
+
 	@override
 	Widget build(BuildContext context) {
 		return Column(
@@ -171,6 +295,12 @@ class _LibraryScreenState extends State<LibraryScreen> {
 								label: Text("Add"),
 							),
 							const SizedBox(width: 10),
+							ElevatedButton.icon(
+								onPressed: _onImportGames,
+								icon: const Icon(Icons.download),
+								label: Text("Import"),
+							),
+							const SizedBox(width: 10),
 							ElevatedButton.icon(
 								onPressed: _loadGames,
 								icon: const Icon(Icons.refresh),
@@ -192,12 +322,14 @@ class _LibraryScreenState extends State<LibraryScreen> {
 									itemBuilder: (context, index) {
 										final game = _games[index];
 										final isSelected = _selectedGame == game;
+										final run = _runs[game.name];
 
 										String name = game.name;
 										String path = "";
 
 										return ListTile(
 											title: Text(game.name, style: TextStyle(fontSize: 18, color: settings.oldDarkMode ? Color(0xFFFFFFFF) : Colors.black)),
+											subtitle: run == null ? null : _buildRunStatus(run),
 											leading: game.icon ?? const Icon(Icons.videogame_asset),
 											tileColor: isSelected ? Colors.grey[800] : null,
 											onTap: () => _launchGame(game.name),
@@ -256,6 +388,19 @@ class _LibraryScreenState extends State<LibraryScreen> {
 																],
 															),
 														);
+														} else if (value == 'cancel') {
+															await tauriInvoke("cancel_run", {"gameName": game.name});
+														} else if (value == 'favourite') {
+															final favourite = !settings.favourites.contains(game.name);
+															await tauriInvoke("set_favourite", {"gameName": game.name, "favourite": favourite});
+
+															setState(() {
+																if (favourite) {
+																	settings.favourites.add(game.name);
+																} else {
+																	settings.favourites.remove(game.name);
+																}
+															});
 														} else if (value == 'delete') {
 															await tauriInvoke("delete_game", {"name": game.name});
 															_loadGames();
@@ -278,6 +423,15 @@ class _LibraryScreenState extends State<LibraryScreen> {
 															value: 'edit',
 															child: Text('Edit'),
 														),
+														if (run != null)
+															PopupMenuItem(
+																value: 'cancel',
+																child: Text('Cancel Script'),
+															),
+														PopupMenuItem(
+															value: 'favourite',
+															child: Text(settings.favourites.contains(game.name) ? 'Remove from Favourites' : 'Add to Favourites'),
+														),
 														PopupMenuItem(
 															value: 'delete',
 															child: Text('Delete'),
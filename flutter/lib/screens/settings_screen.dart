@@ -19,6 +19,10 @@ class _SettingsScreenState extends State<SettingsScreen> {
 	
 	Future<void> _init() async {
 		await settings.loadSettings();
+
+		if (mounted) {
+			setState(() {});
+		}
 	}
 
 	Future<void> _saveSettings() async {
@@ -32,9 +36,31 @@ class _SettingsScreenState extends State<SettingsScreen> {
             if (settings.isDevMode) {
                 logger.add("[settings.dart] Saved Setings");
             }
+		} else if (mounted) {
+			ScaffoldMessenger.of(context).showSnackBar(
+				SnackBar(content: Text(res)),
+			);
 		}
 	}
 
+  /// What makes [rule] run, like "When steam opens".
+  String _describeRule(Map rule) {
+    switch (rule["on"]) {
+      case "schedule":
+        return "On the schedule ${rule["cron"]}";
+      case "startup":
+        return "When Luauncher starts";
+      case "appOpen":
+        return "When ${rule["app"]} opens";
+      case "appClose":
+        return "When ${rule["app"]} closes";
+      case "fileChange":
+        return "When ${rule["path"]} changes";
+      default:
+        return "Unknown trigger";
+    }
+  }
+
   void showBar(String text) {
 		ScaffoldMessenger.of(context).showSnackBar(
 			SnackBar(content: Text(text)),
@@ -123,6 +149,23 @@ class _SettingsScreenState extends State<SettingsScreen> {
 										setState(() => settings.updateIcons = value);
 									},
 								),
+
+								if (settings.rules.isNotEmpty) ...[
+									const SizedBox(height: 30),
+
+									Text("Rules", style: TextStyle(fontSize: 22, fontWeight: FontWeight.bold, color: settings.oldDarkMode ? Color(0xFFFFFFFF) : Colors.black)),
+									Text("Rules are added and changed in \"rules\" in settings.json, here they can only be turned on and off.", style: TextStyle(fontSize: 14, color: settings.oldDarkMode ? Color(0xB3FFFFFF) : Colors.black54)),
+
+									for (final rule in settings.rules)
+										SwitchListTile(
+											title: Text(rule["game"] ?? "", style: TextStyle(fontSize: 18, color: settings.oldDarkMode ? Color(0xFFFFFFFF) : Colors.black)),
+											subtitle: Text(_describeRule(rule)),
+											value: rule["enabled"] ?? true,
+											onChanged: (value) {
+												setState(() => rule["enabled"] = value);
+											},
+										),
+								],
 							],
 						),
 
@@ -160,6 +203,8 @@ class Settings extends ChangeNotifier {
   bool updateIcons = false;
   String version = "Unknown";
 	Map<String, String> gamePaths = {};
+  List<String> favourites = [];
+  List<Map> rules = [];
 
 	Future<void> loadSettings() async {
 		final settings = await tauriInvoke('get_settings');
@@ -174,6 +219,10 @@ class Settings extends ChangeNotifier {
 
     updateIcons = settings["iconupdates"];
 
+    favourites = List<String>.from(settings["favourites"] ?? []);
+
+    rules = List<Map>.from(await tauriInvoke('get_rules') as List);
+
     getVersion();
 	}
 
@@ -185,6 +234,13 @@ class Settings extends ChangeNotifier {
 	Future<String> saveSettings() async {
 		String res = await tauriInvoke('save_settings', {"dark": isDarkMode, "dev": isDevMode, "close": closeAfterOpen, "games": gamePaths, "iconupdates": updateIcons});
 
+    // A rule that was mistyped in settings.json is refused, which shouldn't stop the rest from saving.
+    try {
+      await tauriInvoke('set_rules', {"rules": rules});
+    } catch (e) {
+      res = "Settings saved, but the rules weren't: $e";
+    }
+
         if (oldDarkMode != isDarkMode) {
             if (settings.isDevMode) {
                 logger.add("[settings.dart] Restarting app");
@@ -0,0 +1,171 @@
+import 'package:flutter/material.dart';
+
+import 'tauri_invoke.dart';
+import 'screens/output_screen.dart';
+import 'screens/settings_screen.dart';
+
+final GlobalKey<NavigatorState> navigatorKey = GlobalKey<NavigatorState>();
+
+final Set<int> _seen = {};
+final List<Map<String, dynamic>> _queue = [];
+bool _showing = false;
+bool _retrying = false;
+
+/// Shows the dialogs scripts ask for (confirm, choose, prompt and the pickers) and sends the answers back.
+Future<void> listenForScriptDialogs() async {
+	try {
+		await tauriListen("dialog", (payload) {
+			_enqueue(Map<String, dynamic>.from(payload as Map));
+		});
+
+		// Scripts that asked before the listener was ready.
+		final pending = await tauriInvoke("get_dialogs") as List;
+		for (final dialog in pending) {
+			_enqueue(Map<String, dynamic>.from(dialog));
+		}
+	} catch (e) {
+		logger.add("[script_dialogs.dart] Failed to listen for script dialogs: $e", level: LogLevel.Error);
+	}
+}
+
+void _enqueue(Map<String, dynamic> dialog) {
+	final id = (dialog['id'] as num).toInt();
+
+	if (!_seen.add(id)) {
+		return;
+	}
+
+	_queue.add(dialog);
+	_showNext();
+}
+
+Future<void> _showNext() async {
+	final context = navigatorKey.currentContext;
+
+	if (_showing || _queue.isEmpty) {
+		return;
+	}
+
+	// The navigator isn't built yet, like right after startup, so try again after the next frame.
+	if (context == null) {
+		if (!_retrying) {
+			_retrying = true;
+			WidgetsBinding.instance.addPostFrameCallback((_) {
+				_retrying = false;
+				_showNext();
+			});
+			WidgetsBinding.instance.ensureVisualUpdate();
+		}
+
+		return;
+	}
+
+	_showing = true;
+	final dialog = _queue.removeAt(0);
+
+	if (settings.isDevMode) {
+		logger.add("[script_dialogs.dart] Showing ${dialog['kind']} dialog ${dialog['id']}");
+	}
+
+	final answer = dialog['kind'] == "pickFile" || dialog['kind'] == "pickFolder"
+		? await _pick(dialog)
+		: await showDialog<dynamic>(
+			context: context,
+			barrierDismissible: false,
+			builder: (context) => _buildDialog(context, dialog),
+		);
+
+	try {
+		await tauriInvoke("answer_dialog", {"id": dialog['id'], "answer": answer});
+	} catch (e) {
+		logger.add("[script_dialogs.dart] Failed to answer dialog ${dialog['id']}: $e", level: LogLevel.Warning);
+	}
+
+	_showing = false;
+	_showNext();
+}
+
+/// The pickers are the system's own, so the path is a real one.
+Future<String?> _pick(Map<String, dynamic> dialog) async {
+	try {
+		return await tauriPick({
+			if (dialog['title'] != null) 'title': dialog['title'],
+			'directory': dialog['kind'] == "pickFolder",
+			'filters': [
+				for (final filter in (dialog['filters'] ?? []) as List)
+					{'name': filter['name'], 'extensions': List<String>.from(filter['extensions'])},
+			],
+		});
+	} catch (e) {
+		logger.add("[script_dialogs.dart] Failed to open the picker: $e", level: LogLevel.Error);
+		return null;
+	}
+}
+
+Widget _buildDialog(BuildContext context, Map<String, dynamic> dialog) {
+	final title = Text(dialog['title'] ?? "");
+
+	switch (dialog['kind']) {
+		case "confirm":
+			return AlertDialog(
+				title: title,
+				content: Text(dialog['text'] ?? ""),
+				actions: [
+					TextButton(
+						onPressed: () => Navigator.pop(context, false),
+						child: Text('No'),
+					),
+					TextButton(
+						onPressed: () => Navigator.pop(context, true),
+						child: Text('Yes'),
+					),
+				],
+			);
+
+		case "choose":
+			final options = List<String>.from(dialog['options'] ?? []);
+
+			return AlertDialog(
+				title: title,
+				content: SizedBox(
+					width: 400,
+					child: ListView.builder(
+						shrinkWrap: true,
+						itemCount: options.length,
+						itemBuilder: (context, index) => ListTile(
+							title: Text(options[index]),
+							onTap: () => Navigator.pop(context, options[index]),
+						),
+					),
+				),
+				actions: [
+					TextButton(
+						onPressed: () => Navigator.pop(context, null),
+						child: Text('Cancel'),
+					),
+				],
+			);
+
+		default:
+			final controller = TextEditingController(text: dialog['default'] ?? "");
+
+			return AlertDialog(
+				title: title,
+				content: TextField(
+					controller: controller,
+					autofocus: true,
+					onSubmitted: (value) => Navigator.pop(context, value),
+				),
+				actions: [
+					TextButton(
+						onPressed: () => Navigator.pop(context, null),
+						child: Text('Cancel'),
+					),
+					TextButton(
+						onPressed: () => Navigator.pop(context, controller.text),
+						child: Text('OK'),
+					),
+				],
+			);
+	}
+}
@@ -11,6 +11,12 @@ import 'screens/output_screen.dart';
 @JS('window.__TAURI__.core.invoke')
 external dynamic _invoke(String cmd, [dynamic args]);
 
+@JS('window.__TAURI__.event.listen')
+external dynamic _listen(String event, Function handler);
+
+@JS('window.__TAURI__.dialog.open')
+external dynamic _openDialog(dynamic options);
+
 Future<dynamic> tauriInvoke(String cmd, [Map<String, dynamic>? args]) async {
   if (settings.isDevMode && cmd != "get_logs") {
     logger.add("[tauri_invoke.dart] Invoking command $cmd");
@@ -36,6 +42,16 @@ Future<dynamic> tauriInvoke(String cmd, [Map<String, dynamic>? args]) async {
         }).toList();
       } else if (cmd == "get_games") {
         return List<String>.from(result.map((e) => e.toString()));
+      } else if (cmd == "scan_library_sources") {
+        return result.map((e) => {
+          'source': getProperty(e, 'source'),
+          'name': getProperty(e, 'name'),
+          'command': getProperty(e, 'command'),
+          'icon': getProperty(e, 'icon'),
+          'script': getProperty(e, 'script')
+        }).toList();
+      } else if (cmd == "get_dialogs" || cmd == "get_runs" || cmd == "get_rules") {
+        return result.map((e) => dartify(e)).toList();
       }
     } else if (result is String) {
       return result;
@@ -47,7 +63,8 @@ Future<dynamic> tauriInvoke(String cmd, [Map<String, dynamic>? args]) async {
         'dev': getProperty(result, 'dev'),
         'close': getProperty(result, 'close'),
         'games': getProperty(result, 'games'),
-        'iconupdates': getProperty(result, 'iconupdates')
+        'iconupdates': getProperty(result, 'iconupdates'),
+        'favourites': dartify(getProperty(result, 'favourites'))
       };
     }
 
@@ -58,6 +75,22 @@ Future<dynamic> tauriInvoke(String cmd, [Map<String, dynamic>? args]) async {
   }
 }
 
+/// Calls [handler] with the payload every time the backend emits [event]. Returns a function that stops listening.
+Future<void Function()> tauriListen(String event, void Function(dynamic payload) handler) async {
+  final unlisten = await promiseToFuture(_listen(event, allowInterop((e) {
+    handler(dartify(getProperty(e, 'payload')));
+  })));
+
+  return () => callMethod(unlisten, 'call', [null]);
+}
+
+/// Opens the system's file or folder picker through the dialog plugin, which gives real paths unlike the webview's own. Returns null if it was cancelled.
+Future<String?> tauriPick(Map<String, dynamic> options) async {
+  final result = await promiseToFuture(_openDialog(jsify(options)));
+
+  return result is String ? result : null;
+}
+
 Future<Image> base64ToImage(String base64String) async {
 	if (base64String.isEmpty) {
 		throw Exception("Base64 icon data is empty");
@@ -0,0 +1,137 @@
+-- Helpers every script gets without having to require anything. They ship with Luauncher,
+-- so what's here goes with apiVersion.
+
+strings = require("std.strings")
+tables = require("std.tables")
+
+-- Stops the script with an error if this Luauncher's script API is older than version.
+function requireApi(version)
+    if apiVersion < version then
+        error(string.format("This script needs version %d of the script API but this Luauncher (%s) has version %d. Update Luauncher to run it.", version, launcherVersion, apiVersion), 2)
+    end
+end
+
+-- Picks choices[system], or choices.default if there's nothing for this system. Functions
+-- are called and their results returned, anything else is returned as is.
+function onSystem(choices)
+    local choice = choices[system]
+
+    if choice == nil then
+        choice = choices.default
+    end
+
+    if type(choice) == "function" then
+        return choice()
+    end
+
+    return choice
+end
+
+-- Calls fn(attempt) until it works, meaning it doesn't error or return false or nil, and
+-- returns what it returned. After the last attempt its error is raised, or its result returned.
+-- options.attempts defaults to 3 and options.delay (milliseconds between attempts) to 1000.
+function retry(fn, options)
+    options = options or {}
+
+    local attempts = options.attempts or 3
+    local delay = options.delay or 1000
+    local results
+
+    for attempt = 1, attempts do
+        results = table.pack(pcall(fn, attempt))
+
+        if results[1] and results[2] then
+            return table.unpack(results, 2, results.n)
+        end
+
+        if attempt < attempts then
+            sleep(delay)
+        end
+    end
+
+    if not results[1] then
+        error(results[2], 2)
+    end
+
+    return table.unpack(results, 2, results.n)
+end
+
+-- Calls check every interval milliseconds (100 by default) until it returns something other
+-- than false or nil and returns that, or gives up after timeout milliseconds and returns nil.
+function withTimeout(timeout, check, interval)
+    interval = interval or 100
+
+    local waited = 0
+
+    while true do
+        local result = check()
+
+        if result then
+            return result
+        end
+
+        if waited >= timeout then
+            return nil
+        end
+
+        sleep(interval)
+        waited = waited + interval
+    end
+end
+
+-- A target is an app name, or a table like { app = "steam" } or { window = "Steam", options = { match = "glob" } }.
+local function isOpen(target)
+    if type(target) == "string" then
+        return isAppOpen(target)
+    elseif target.window then
+        return isWindowOpen(target.window, target.options)
+    else
+        return isAppOpen(target.app, target.options)
+    end
+end
+
+-- Waits until any of the targets is open and returns it, or nil after timeout milliseconds.
+function waitAny(targets, timeout)
+    return withTimeout(timeout, function()
+        for _, target in ipairs(targets) do
+            if isOpen(target) then
+                return target
+            end
+        end
+    end)
+end
+
+-- Waits until all of the targets are open. Returns false if they weren't after timeout milliseconds.
+function waitAll(targets, timeout)
+    return withTimeout(timeout, function()
+        for _, target in ipairs(targets) do
+            if not isOpen(target) then
+                return false
+            end
+        end
+
+        return true
+    end) == true
+end
+
+-- Opens app and waits for it to start. Returns false if it didn't within the timeout.
+-- options.args are passed to the app, options.process is the name to wait for (the file
+-- name of app by default), options.timeout defaults to 60000 and options.untilClosed also
+-- waits for it to close again.
+function launchAndWait(app, options)
+    options = options or {}
+
+    local process = options.process or app:match("([^/\\]+)$")
+
+    openApp(app, options.args)
+
+    if not waitUntilAppOpen(process, options.timeout or 60000) then
+        return false
+    end
+
+    if options.untilClosed then
+        waitUntilAppClose(process)
+    end
+
+    return true
+end
@@ -0,0 +1,63 @@
+-- String helpers that ship with Luauncher. Use them with local strings = require("std.strings")
+
+local strings = {}
+
+-- Removes spaces, tabs and newlines from both ends.
+function strings.trim(text)
+    return (text:gsub("^%s+", ""):gsub("%s+$", ""))
+end
+
+-- Splits text on a separator, which is matched as plain text. Empty parts are kept.
+function strings.split(text, separator)
+    separator = separator or ","
+
+    local parts = {}
+    local start = 1
+
+    if separator == "" then
+        for i = 1, #text do
+            parts[#parts + 1] = text:sub(i, i)
+        end
+        return parts
+    end
+
+    while true do
+        local first, last = text:find(separator, start, true)
+
+        if not first then
+            parts[#parts + 1] = text:sub(start)
+            return parts
+        end
+
+        parts[#parts + 1] = text:sub(start, first - 1)
+        start = last + 1
+    end
+end
+
+function strings.startsWith(text, prefix)
+    return text:sub(1, #prefix) == prefix
+end
+
+function strings.endsWith(text, suffix)
+    return suffix == "" or text:sub(-#suffix) == suffix
+end
+
+-- Whether text has part in it, matched as plain text.
+function strings.contains(text, part)
+    return text:find(part, 1, true) ~= nil
+end
+
+-- Replaces every plain text match of old with new.
+function strings.replace(text, old, new)
+    if old == "" then
+        return text
+    end
+
+    return strings.join(strings.split(text, old), new)
+end
+
+function strings.join(parts, separator)
+    return table.concat(parts, separator or "")
+end
+
+return strings
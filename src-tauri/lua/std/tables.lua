@@ -0,0 +1,88 @@
+-- Table helpers that ship with Luauncher. Use them with local tables = require("std.tables")
+
+local tables = {}
+
+-- Whether the list has value in it.
+function tables.contains(list, value)
+    for _, item in ipairs(list) do
+        if item == value then
+            return true
+        end
+    end
+
+    return false
+end
+
+-- The keys of a table, in no particular order.
+function tables.keys(map)
+    local keys = {}
+
+    for key in pairs(map) do
+        keys[#keys + 1] = key
+    end
+
+    return keys
+end
+
+-- How many entries a table has, counting ones that aren't in the list part.
+function tables.count(map)
+    local count = 0
+
+    for _ in pairs(map) do
+        count = count + 1
+    end
+
+    return count
+end
+
+-- A new list with fn(item) for each item.
+function tables.map(list, fn)
+    local mapped = {}
+
+    for i, item in ipairs(list) do
+        mapped[i] = fn(item)
+    end
+
+    return mapped
+end
+
+-- A new list with the items fn(item) returned true for.
+function tables.filter(list, fn)
+    local kept = {}
+
+    for _, item in ipairs(list) do
+        if fn(item) then
+            kept[#kept + 1] = item
+        end
+    end
+
+    return kept
+end
+
+-- A copy of a table. Tables inside it are copied too when deep is true.
+function tables.copy(map, deep)
+    local copy = {}
+
+    for key, value in pairs(map) do
+        if deep and type(value) == "table" then
+            copy[key] = tables.copy(value, true)
+        else
+            copy[key] = value
+        end
+    end
+
+    return copy
+end
+
+-- Copies the entries of each table after the first into the first one, later ones winning.
+function tables.merge(into, ...)
+    for _, from in ipairs({ ... }) do
+        for key, value in pairs(from) do
+            into[key] = value
+        end
+    end
+
+    return into
+end
+
+return tables
@@ -0,0 +1,216 @@
+use mlua::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::Command;
+
+use crate::files::expand_home;
+use crate::output;
+
+/// How long to keep reading output after a command exits or is killed. Anything it started in
+/// the background can keep the pipes open, and the script shouldn't wait on those.
+const KILL_GRACE: Duration = Duration::from_millis(500);
+
+#[derive(Clone, Debug)]
+pub(crate) enum Program {
+    /// Run through the platform shell, `cmd /C` on Windows and `sh -c` everywhere else.
+    Shell(String),
+    /// Run directly with these arguments, no shell involved.
+    Argv(Vec<String>),
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct CommandRequest {
+    pub(crate) program: Program,
+    pub(crate) cwd: Option<String>,
+    pub(crate) env: HashMap<String, String>,
+    pub(crate) timeout: Option<Duration>,
+    /// Whether each line of output is also logged as it comes in.
+    pub(crate) stream: bool,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CommandOutput {
+    /// `None` if the command was killed, by a signal or for taking too long.
+    pub(crate) code: Option<i32>,
+    pub(crate) stdout: Vec<u8>,
+    pub(crate) stderr: Vec<u8>,
+    pub(crate) timed_out: bool,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct CommandOptions {
+    cwd: Option<String>,
+    env: HashMap<String, String>,
+    /// In milliseconds.
+    timeout: Option<u64>,
+    stream: bool,
+}
+
+impl CommandRequest {
+    /// `command` is a string for the shell or an array of the program and its arguments.
+    pub(crate) fn from_lua(lua: &Lua, command: LuaValue, options: Option<LuaValue>) -> LuaResult<Self> {
+        let program = match command {
+            LuaValue::String(text) => Program::Shell(text.to_str()?.to_owned()),
+            LuaValue::Table(_) => {
+                let argv: Vec<String> = lua.from_value(command)?;
+
+                if argv.is_empty() {
+                    return Err(LuaError::external("runCommand needs at least the program to run"));
+                }
+
+                Program::Argv(argv)
+            }
+            other => return Err(LuaError::external(format!("runCommand takes a string or a table, not {}", other.type_name()))),
+        };
+
+        let options: CommandOptions = match options {
+            Some(value) if !value.is_nil() => lua.from_value(value)?,
+            _ => CommandOptions::default(),
+        };
+
+        Ok(CommandRequest {
+            program,
+            cwd: options.cwd,
+            env: options.env,
+            timeout: options.timeout.map(Duration::from_millis),
+            stream: options.stream,
+        })
+    }
+
+    /// The command as it would be typed, for logs and traces.
+    pub(crate) fn display(&self) -> String {
+        match &self.program {
+            Program::Shell(text) => text.clone(),
+            Program::Argv(argv) => argv.join(" "),
+        }
+    }
+}
+
+impl CommandOutput {
+    pub(crate) fn to_lua(&self, lua: &Lua) -> LuaResult<LuaTable> {
+        let table = lua.create_table()?;
+
+        table.set("code", self.code)?;
+        table.set("stdout", lua.create_string(&self.stdout)?)?;
+        table.set("stderr", lua.create_string(&self.stderr)?)?;
+        table.set("timedOut", self.timed_out)?;
+
+        Ok(table)
+    }
+}
+
+/// Reads `pipe` into `all`, logging each line as it arrives when `stream` is on.
+async fn collect(pipe: Option<impl AsyncRead + Unpin>, all: Arc<Mutex<Vec<u8>>>, stream: bool, level: output::LogLevel) {
+    let Some(pipe) = pipe else {
+        return;
+    };
+
+    let mut reader = BufReader::new(pipe);
+    let mut line = Vec::new();
+
+    while reader.read_until(b'\n', &mut line).await.is_ok_and(|read| read > 0) {
+        if stream {
+            let text = String::from_utf8_lossy(&line);
+            output::add_log(format!("[runCommand] {}", text.trim_end()), level.clone(), false);
+        }
+
+        all.lock().unwrap().append(&mut line);
+    }
+}
+
+pub(crate) async fn run(request: CommandRequest) -> Result<CommandOutput, String> {
+    let mut command = match &request.program {
+        Program::Shell(text) if cfg!(target_os = "windows") => {
+            let mut command = Command::new("cmd");
+            command.args(["/C", text]);
+            command
+        }
+        Program::Shell(text) => {
+            let mut command = Command::new("sh");
+            command.args(["-c", text]);
+            command
+        }
+        Program::Argv(argv) => {
+            let mut command = Command::new(&argv[0]);
+            command.args(&argv[1..]);
+            command
+        }
+    };
+
+    if let Some(cwd) = &request.cwd {
+        command.current_dir(expand_home(cwd));
+    }
+
+    command.envs(&request.env)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+
+    let mut child = command.spawn()
+        .map_err(|e| format!("Failed to run '{}': {e}", request.display()))?;
+
+    let (stdout, stderr) = (Arc::new(Mutex::new(Vec::new())), Arc::new(Mutex::new(Vec::new())));
+    let readers = [
+        tokio::spawn(collect(child.stdout.take(), stdout.clone(), request.stream, output::LogLevel::Info)),
+        tokio::spawn(collect(child.stderr.take(), stderr.clone(), request.stream, output::LogLevel::Warning)),
+    ];
+
+    let (status, timed_out) = match request.timeout {
+        Some(timeout) => match tokio::time::timeout(timeout, child.wait()).await {
+            Ok(status) => (status, false),
+            Err(_) => {
+                let _ = child.kill().await;
+                (child.wait().await, true)
+            }
+        },
+        None => (child.wait().await, false),
+    };
+
+    let status = status.map_err(|e| format!("Failed to wait for '{}': {e}", request.display()))?;
+
+    let deadline = tokio::time::Instant::now() + KILL_GRACE;
+
+    for mut reader in readers {
+        if tokio::time::timeout_at(deadline, &mut reader).await.is_err() {
+            reader.abort();
+        }
+    }
+
+    let take = |buffer: &Arc<Mutex<Vec<u8>>>| std::mem::take(&mut *buffer.lock().unwrap());
+
+    Ok(CommandOutput {
+        code: if timed_out { None } else { status.code() },
+        stdout: take(&stdout),
+        stderr: take(&stderr),
+        timed_out,
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn background_children_dont_hold_up_the_script() {
+        let request = CommandRequest {
+            program: Program::Shell("echo started; sleep 30 &".to_owned()),
+            cwd: None,
+            env: HashMap::new(),
+            timeout: None,
+            stream: false,
+        };
+
+        let started = std::time::Instant::now();
+        let output = run(request).await.unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(output.code, Some(0));
+        assert_eq!(output.stdout, b"started\n");
+    }
+}
@@ -0,0 +1,218 @@
+use mlua::prelude::*;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use tauri::{Emitter, Manager};
+use tokio::sync::oneshot;
+
+use crate::os_layer::os;
+
+/// Filters for `pickFile`, like `{ name = "Saves", extensions = { "sav" } }`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct FileFilter {
+    pub(crate) name: String,
+    pub(crate) extensions: Vec<String>,
+}
+
+/// A question for the user. Sent to the frontend as is, with `kind` saying which one it is.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub(crate) enum Dialog {
+    Confirm { title: String, text: String },
+    Choose { title: String, options: Vec<String> },
+    Prompt { title: String, default: Option<String> },
+    PickFile { title: Option<String>, filters: Vec<FileFilter> },
+    PickFolder { title: Option<String> },
+}
+
+impl Dialog {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            Dialog::Confirm { .. } => "confirm",
+            Dialog::Choose { .. } => "choose",
+            Dialog::Prompt { .. } => "prompt",
+            Dialog::PickFile { .. } => "pickFile",
+            Dialog::PickFolder { .. } => "pickFolder",
+        }
+    }
+
+    pub(crate) fn title(&self) -> &str {
+        match self {
+            Dialog::Confirm { title, .. } | Dialog::Choose { title, .. } | Dialog::Prompt { title, .. } => title,
+            Dialog::PickFile { title, .. } | Dialog::PickFolder { title } => title.as_deref().unwrap_or(""),
+        }
+    }
+
+    /// What a dialog answers when nobody is there to ask: yes, the first option and the default text.
+    pub(crate) fn default_answer(&self) -> Value {
+        match self {
+            Dialog::Confirm { .. } => Value::Bool(true),
+            Dialog::Choose { options, .. } => options.first().cloned().map(Value::String).unwrap_or(Value::Null),
+            Dialog::Prompt { default, .. } => default.clone().map(Value::String).unwrap_or(Value::Null),
+            Dialog::PickFile { .. } | Dialog::PickFolder { .. } => Value::Null,
+        }
+    }
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct PendingDialog {
+    id: u64,
+    #[serde(flatten)]
+    dialog: Dialog,
+}
+
+type Waiting = HashMap<u64, (PendingDialog, oneshot::Sender<Value>)>;
+
+/// Dialogs waiting on the frontend. They're kept here as well as sent as an event, so a
+/// frontend that wasn't listening yet can still pick them up with `get_dialogs`.
+static PENDING: Lazy<Mutex<Waiting>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) fn pending() -> Vec<PendingDialog> {
+    let mut dialogs: Vec<PendingDialog> = PENDING.lock().unwrap().values().map(|(dialog, _)| dialog.clone()).collect();
+    dialogs.sort_by_key(|dialog| dialog.id);
+    dialogs
+}
+
+/// Hands the frontend's answer to the script waiting on dialog `id`. `null` is cancelled.
+pub(crate) fn answer(id: u64, value: Value) -> Result<(), String> {
+    let (_, sender) = PENDING.lock().unwrap()
+        .remove(&id)
+        .ok_or_else(|| format!("No dialog with id {id} is waiting"))?;
+
+    sender.send(value).map_err(|_| "The script stopped waiting for this dialog".to_owned())
+}
+
+/// Removes the dialog if the script stops waiting, like when it's cancelled.
+struct Cleanup(u64);
+
+impl Drop for Cleanup {
+    fn drop(&mut self) {
+        PENDING.lock().unwrap().remove(&self.0);
+    }
+}
+
+async fn ask_frontend(app: &tauri::AppHandle, dialog: Dialog) -> Result<Value, String> {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let pending = PendingDialog { id, dialog };
+    let (sender, receiver) = oneshot::channel();
+
+    PENDING.lock().unwrap().insert(id, (pending.clone(), sender));
+    let _cleanup = Cleanup(id);
+
+    // Games launched from a shortcut run with the window hidden, so it has to come up to ask.
+    if let Some(window) = app.get_webview_window("Luauncher") {
+        let _ = window.show();
+        let _ = window.unminimize();
+        let _ = window.set_focus();
+    }
+
+    app.emit("dialog", pending).map_err(|e| format!("Failed to show the dialog: {e}"))?;
+
+    receiver.await.map_err(|_| "The dialog was closed without an answer".to_owned())
+}
+
+/// Asks on the terminal, for when the launcher runs without its window.
+fn ask_terminal(dialog: &Dialog) -> io::Result<Value> {
+    let mut stdout = io::stdout();
+    let mut read_line = || -> io::Result<String> {
+        stdout.flush()?;
+        let mut line = String::new();
+        io::stdin().lock().read_line(&mut line)?;
+        Ok(line.trim().to_owned())
+    };
+
+    match dialog {
+        Dialog::Confirm { title, text } => {
+            print!("{title}\n{text} [y/n] ");
+            Ok(Value::Bool(read_line()?.to_lowercase().starts_with('y')))
+        }
+        Dialog::Choose { title, options } => {
+            println!("{title}");
+            for (i, option) in options.iter().enumerate() {
+                println!("  {}. {option}", i + 1);
+            }
+            print!("Number (empty to cancel): ");
+
+            let chosen = read_line()?.parse::<usize>().ok()
+                .and_then(|n| options.get(n.wrapping_sub(1)))
+                .cloned();
+            Ok(chosen.map(Value::String).unwrap_or(Value::Null))
+        }
+        Dialog::Prompt { title, default } => {
+            match default {
+                Some(default) => print!("{title} [{default}]: "),
+                None => print!("{title}: "),
+            }
+
+            let line = read_line()?;
+            Ok(if line.is_empty() { dialog.default_answer() } else { Value::String(line) })
+        }
+        Dialog::PickFile { .. } | Dialog::PickFolder { .. } => {
+            print!("{} (path, empty to cancel): ", dialog.title());
+            let line = read_line()?;
+            Ok(if line.is_empty() { Value::Null } else { Value::String(line) })
+        }
+    }
+}
+
+/// Shows `dialog` to the user and waits for the answer. It goes through the frontend, which opens
+/// the file pickers with the dialog plugin so they get real paths, or the terminal if there's no window.
+pub(crate) async fn ask(dialog: Dialog) -> Result<Value, String> {
+    match crate::app_handle() {
+        Some(app) => ask_frontend(app, dialog).await,
+        None => tokio::task::spawn_blocking(move || ask_terminal(&dialog))
+            .await
+            .map_err(|e| e.to_string())?
+            .map_err(|e| format!("Failed to ask on the terminal: {e}")),
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct PickOptions {
+    title: Option<String>,
+    filters: Vec<FileFilter>,
+}
+
+/// Builds `confirm`, `choose`, `prompt`, `pickFile` and `pickFolder`.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    let globals = lua.globals();
+
+    globals.set("confirm", lua.create_async_function(|lua, (title, text): (String, String)| async move {
+        let answer = os(&lua).dialog(Dialog::Confirm { title, text }).await?;
+        Ok(answer.as_bool().unwrap_or(false))
+    })?)?;
+
+    globals.set("choose", lua.create_async_function(|lua, (title, options): (String, Vec<String>)| async move {
+        let answer = os(&lua).dialog(Dialog::Choose { title, options }).await?;
+        Ok(answer.as_str().map(str::to_owned))
+    })?)?;
+
+    globals.set("prompt", lua.create_async_function(|lua, (title, default): (String, Option<String>)| async move {
+        let answer = os(&lua).dialog(Dialog::Prompt { title, default }).await?;
+        Ok(answer.as_str().map(str::to_owned))
+    })?)?;
+
+    globals.set("pickFile", lua.create_async_function(|lua, options: Option<LuaValue>| async move {
+        let options: PickOptions = match options {
+            Some(LuaValue::String(title)) => PickOptions { title: Some(title.to_str()?.to_owned()), ..Default::default() },
+            Some(value) if !value.is_nil() => lua.from_value(value)?,
+            _ => PickOptions::default(),
+        };
+
+        let answer = os(&lua).dialog(Dialog::PickFile { title: options.title, filters: options.filters }).await?;
+        Ok(answer.as_str().map(str::to_owned))
+    })?)?;
+
+    globals.set("pickFolder", lua.create_async_function(|lua, title: Option<String>| async move {
+        let answer = os(&lua).dialog(Dialog::PickFolder { title }).await?;
+        Ok(answer.as_str().map(str::to_owned))
+    })?)?;
+
+    Ok(())
+}
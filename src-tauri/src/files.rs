@@ -1,6 +1,6 @@
 use directories::BaseDirs;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Component, PathBuf};
 use serde::{Serialize, Deserialize};
 use std::{env, fs, io::Write, fs::File, process::Command, path::Path};
 use uuid::Uuid;
@@ -19,6 +19,8 @@ use windows::{
 use std::os::unix::fs::PermissionsExt;
 
 use crate::output;
+use crate::saves::SaveConfig;
+use crate::scheduler::Rule;
 
 #[cfg(target_os = "windows")]
 const EMBEDDED_BIN: &[u8] = include_bytes!("../updater/target/release/updater.exe");
@@ -45,11 +47,42 @@ pub(crate) fn get_icon_dir() -> Option<PathBuf> {
     get_app_base().map(|base| base.join("icons"))
 }
 
+pub(crate) fn get_hooks_dir() -> Option<PathBuf> {
+    get_scripts_dir().map(|dir| dir.join("hooks"))
+}
+
+/// Modules scripts can `require`.
+pub(crate) fn get_lib_dir() -> Option<PathBuf> {
+    get_scripts_dir().map(|dir| dir.join("lib"))
+}
+
+pub(crate) fn get_templates_dir() -> Option<PathBuf> {
+    get_app_base().map(|base| base.join("templates"))
+}
+
+/// Paths in settings can start with `~` for the home folder.
+pub(crate) fn expand_home(path: &str) -> PathBuf {
+    match path.strip_prefix('~') {
+        Some(rest) => match BaseDirs::new() {
+            Some(dirs) => dirs.home_dir().join(rest.trim_start_matches(['/', '\\'])),
+            None => PathBuf::from(path),
+        },
+        None => PathBuf::from(path),
+    }
+}
+
+/// Whether `name` is a single file or folder name, so joining it onto a folder stays inside it.
+pub(crate) fn is_plain_name(name: &str) -> bool {
+    let mut components = Path::new(name).components();
+
+    !name.contains(['/', '\\']) && matches!((components.next(), components.next()), (Some(Component::Normal(_)), None))
+}
+
 pub(crate) fn get_file_content(path: String) -> String {
     fs::read_to_string(path).unwrap_or_else(|_| String::new())
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Default)]
 pub(crate) struct Settings {
     pub(crate) dark: bool,
     pub(crate) dev: bool,
@@ -57,6 +90,50 @@ pub(crate) struct Settings {
     #[serde(default)]
     pub(crate) games: HashMap<String, String>,
     pub(crate) iconupdates: bool,
+    #[serde(default)]
+    pub(crate) saves: HashMap<String, SaveConfig>,
+    /// Folders the user lets scripts use through `fs`, on top of each script's own data folder.
+    #[serde(default)]
+    pub(crate) fs_roots: Vec<String>,
+    /// Extra permissions like `"network"` by game name, `"*"` applies to every script.
+    #[serde(default)]
+    pub(crate) capabilities: HashMap<String, Vec<String>>,
+    /// Scripts to run on a schedule or when something happens, see `scheduler`.
+    #[serde(default)]
+    pub(crate) rules: Vec<Rule>,
+    /// Games pinned to the top of the tray menu.
+    #[serde(default)]
+    pub(crate) favourites: Vec<String>,
+    /// The last games launched, newest first.
+    #[serde(default)]
+    pub(crate) recent: Vec<String>,
+}
+
+/// How many games `Settings.recent` remembers.
+const RECENT_LIMIT: usize = 5;
+
+/// Puts `game` at the front of `Settings.recent`.
+pub(crate) fn add_recent(game: &str) -> std::io::Result<()> {
+    let Some(mut settings) = load_settings()? else {
+        return Ok(());
+    };
+
+    settings.recent.retain(|recent| recent != game);
+    settings.recent.insert(0, game.to_owned());
+    settings.recent.truncate(RECENT_LIMIT);
+
+    save_settings(&settings)
+}
+
+/// Whether the user gave `game`, or every script, the capability `name`.
+pub(crate) fn has_capability(game: Option<&str>, name: &str) -> std::io::Result<bool> {
+    let Some(settings) = load_settings()? else {
+        return Ok(false);
+    };
+
+    Ok(["*"].into_iter().chain(game)
+        .filter_map(|key| settings.capabilities.get(key))
+        .any(|caps| caps.iter().any(|cap| cap == name)))
 }
 
 pub(crate) fn save_settings(settings: &Settings) -> std::io::Result<()> {
@@ -128,6 +205,7 @@ pub(crate) fn make_dirs() {
     let settings_path = app_dir.join("settings.json");
     let scripts_dir = app_dir.join("scripts");
     let icon_dir = app_dir.join("icons");
+    let templates_dir = app_dir.join("templates");
 
     if let Err(e) = fs::create_dir_all(&scripts_dir) {
         output::add_log(format!("[Creating Scripts Directory] Failed to create scripts directory: {e}"), output::LogLevel::Error, false);
@@ -137,6 +215,10 @@ pub(crate) fn make_dirs() {
         output::add_log(format!("[Creating Icon Directory] Failed to create icon directory: {e}"), output::LogLevel::Error, false);
     }
 
+    if let Err(e) = fs::create_dir_all(&templates_dir) {
+        output::add_log(format!("[Creating Templates Directory] Failed to create templates directory: {e}"), output::LogLevel::Error, false);
+    }
+
     if !settings_path.exists() {
         let default_settings = r#"{
             "dark": true,
@@ -0,0 +1,63 @@
+use mlua::prelude::*;
+use serde_json::Value;
+
+/// TOML dates have no Lua equivalent, so they come back as their TOML text, like `"1979-05-27"`.
+fn toml_to_json(value: toml::Value) -> Value {
+    match value {
+        toml::Value::String(text) => Value::String(text),
+        toml::Value::Integer(number) => Value::from(number),
+        toml::Value::Float(number) => Value::from(number),
+        toml::Value::Boolean(flag) => Value::Bool(flag),
+        toml::Value::Datetime(date) => Value::String(date.to_string()),
+        toml::Value::Array(items) => Value::Array(items.into_iter().map(toml_to_json).collect()),
+        toml::Value::Table(table) => Value::Object(table.into_iter().map(|(key, value)| (key, toml_to_json(value))).collect()),
+    }
+}
+
+/// Builds the `json` and `toml` tables, each with `encode` and `decode`.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    let json = lua.create_table()?;
+
+    json.set("encode", lua.create_function(|lua, (value, pretty): (LuaValue, Option<bool>)| {
+        let value: Value = lua.from_value(value)
+            .map_err(|e| LuaError::external(format!("Can't encode as JSON: {e}")))?;
+
+        let text = if pretty.unwrap_or(false) {
+            serde_json::to_string_pretty(&value)
+        } else {
+            serde_json::to_string(&value)
+        };
+
+        text.map_err(LuaError::external)
+    })?)?;
+
+    json.set("decode", lua.create_function(|lua, text: String| {
+        let value: Value = serde_json::from_str(&text)
+            .map_err(|e| LuaError::external(format!("Invalid JSON: {e}")))?;
+
+        lua.to_value(&value)
+    })?)?;
+
+    // Decoded JSON nulls are this value rather than nil, so keys holding null aren't lost.
+    json.set("null", lua.null())?;
+
+    let toml = lua.create_table()?;
+
+    toml.set("encode", lua.create_function(|lua, value: LuaTable| {
+        let value: toml::Table = lua.from_value(LuaValue::Table(value))
+            .map_err(|e| LuaError::external(format!("Can't encode as TOML: {e}")))?;
+
+        toml::to_string(&value).map_err(|e| LuaError::external(format!("Can't encode as TOML: {e}")))
+    })?)?;
+
+    toml.set("decode", lua.create_function(|lua, text: String| {
+        let value: toml::Table = text.parse()
+            .map_err(|e| LuaError::external(format!("Invalid TOML: {e}")))?;
+
+        lua.to_value(&toml_to_json(toml::Value::Table(value)))
+    })?)?;
+
+    let globals = lua.globals();
+    globals.set("json", json)?;
+    globals.set("toml", toml)
+}
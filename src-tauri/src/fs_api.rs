@@ -0,0 +1,312 @@
+use mlua::prelude::*;
+use serde::Serialize;
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::time::Duration;
+
+use crate::files::{self, expand_home, get_app_base};
+use crate::lua_utils::current_game;
+use crate::os_layer::os;
+
+const WATCH_INTERVAL: u64 = 500;
+
+/// A game's own folder. Names that aren't a plain folder name have none, so they can't point elsewhere.
+pub(crate) fn get_data_dir(game: &str) -> Option<PathBuf> {
+    get_app_base()
+        .filter(|_| files::is_plain_name(game))
+        .map(|base| base.join("data").join(game))
+}
+
+/// Follows symlinks for the part of `path` that exists, so a link can't be used to get out of a root.
+/// A link that points nowhere counts as existing and fails to resolve, since writing through it
+/// would create its target wherever that is.
+fn real_path(path: &Path) -> io::Result<PathBuf> {
+    let mut existing = path.to_path_buf();
+    let mut rest = Vec::new();
+
+    while existing.symlink_metadata().is_err() {
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                rest.push(name.to_owned());
+                existing = parent.to_path_buf();
+            }
+            _ => return Ok(path.to_path_buf()),
+        }
+    }
+
+    let mut real = existing.canonicalize()?;
+    real.extend(rest.into_iter().rev());
+    Ok(real)
+}
+
+/// The folders a script may touch: its own data folder and the roots approved in `Settings.fs_roots`.
+fn roots(game: Option<&str>) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = game.and_then(get_data_dir).into_iter().collect();
+
+    if let Ok(Some(settings)) = files::load_settings() {
+        // A relative root would depend on the folder Luauncher was started in.
+        roots.extend(settings.fs_roots.iter().map(|root| expand_home(root)).filter(|root| root.is_absolute()));
+    }
+
+    roots
+}
+
+/// Turns a path from a script into a real path. Relative paths are in the script's data folder,
+/// `..` is never allowed and anything outside the allowed roots is an error.
+pub(crate) fn resolve(game: Option<&str>, path: &str) -> Result<PathBuf, String> {
+    let requested = expand_home(path);
+
+    if requested.components().any(|c| c == Component::ParentDir) {
+        return Err(format!("'{path}' isn't allowed, paths can't contain '..'"));
+    }
+
+    let full = if requested.is_absolute() {
+        requested
+    } else {
+        let data_dir = game.and_then(get_data_dir)
+            .ok_or_else(|| format!("'{path}' is relative, but this script has no data folder"))?;
+        data_dir.join(requested)
+    };
+
+    let real = real_path(&full).map_err(|e| format!("'{path}' couldn't be resolved: {e}"))?;
+
+    let allowed = roots(game).iter().any(|root| {
+        real_path(root).is_ok_and(|root| real.starts_with(root))
+    });
+
+    if !allowed {
+        return Err(format!("'{path}' is outside the folders this script may use"));
+    }
+
+    Ok(full)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Entry {
+    name: String,
+    is_dir: bool,
+    size: u64,
+}
+
+fn copy_recursive(from: &Path, to: &Path) -> io::Result<()> {
+    if from.is_dir() {
+        fs::create_dir_all(to)?;
+
+        for entry in fs::read_dir(from)? {
+            let entry = entry?;
+            copy_recursive(&entry.path(), &to.join(entry.file_name()))?;
+        }
+
+        return Ok(());
+    }
+
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    fs::copy(from, to).map(|_| ())
+}
+
+fn move_path(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Renaming fails across drives, where it has to be a copy instead.
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+
+    copy_recursive(from, to)?;
+
+    if from.is_dir() {
+        fs::remove_dir_all(from)
+    } else {
+        fs::remove_file(from)
+    }
+}
+
+/// A value that changes whenever a file, or anything directly in a folder, is added, removed or modified.
+pub(crate) fn signature(path: &Path) -> u64 {
+    let mut hasher = DefaultHasher::new();
+
+    let stamp = |path: &Path, hasher: &mut DefaultHasher| {
+        if let Ok(meta) = fs::metadata(path) {
+            meta.len().hash(hasher);
+            meta.modified().ok().hash(hasher);
+        }
+    };
+
+    path.exists().hash(&mut hasher);
+    stamp(path, &mut hasher);
+
+    if path.is_dir() {
+        let mut children: Vec<PathBuf> = fs::read_dir(path)
+            .map(|entries| entries.flatten().map(|e| e.path()).collect())
+            .unwrap_or_default();
+        children.sort();
+
+        for child in children {
+            child.hash(&mut hasher);
+            stamp(&child, &mut hasher);
+        }
+    }
+
+    hasher.finish()
+}
+
+pub(crate) fn path_arg(lua: &Lua, path: &str) -> LuaResult<PathBuf> {
+    resolve(current_game(lua)?.as_deref(), path).map_err(LuaError::external)
+}
+
+/// Runs a change through the OS layer, so simulations record it instead of touching the disk.
+pub(crate) fn change(lua: &Lua, action: &str, detail: String, apply: impl FnOnce() -> io::Result<()>) -> LuaResult<()> {
+    os(lua).modify_files(action, &detail, Box::new(apply))
+}
+
+/// Builds the `fs` table. Every path goes through `resolve` first.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    let module = lua.create_table()?;
+
+    module.set("read", lua.create_function(|lua, path: String| {
+        fs::read_to_string(path_arg(lua, &path)?)
+            .map_err(|e| LuaError::external(format!("Failed to read '{path}': {e}")))
+    })?)?;
+
+    module.set("write", lua.create_function(|lua, (path, content, append): (String, LuaString, Option<bool>)| {
+        let full = path_arg(lua, &path)?;
+        let content = content.as_bytes().to_vec();
+
+        change(lua, "fs.write", path.clone(), move || {
+            if let Some(parent) = full.parent() {
+                fs::create_dir_all(parent)?;
+            }
+
+            fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(append.unwrap_or(false))
+                .truncate(!append.unwrap_or(false))
+                .open(&full)?
+                .write_all(&content)
+        })
+    })?)?;
+
+    module.set("list", lua.create_function(|lua, path: Option<String>| {
+        let full = path_arg(lua, path.as_deref().unwrap_or(""))?;
+
+        if !full.exists() && path.is_none() {
+            return lua.create_table().map(LuaValue::Table);
+        }
+
+        let mut entries: Vec<Entry> = fs::read_dir(&full)
+            .map_err(|e| LuaError::external(format!("Failed to list '{}': {e}", path.as_deref().unwrap_or("."))))?
+            .flatten()
+            .map(|entry| {
+                let meta = entry.metadata().ok();
+
+                Entry {
+                    name: entry.file_name().to_string_lossy().to_string(),
+                    is_dir: meta.as_ref().is_some_and(|m| m.is_dir()),
+                    size: meta.map(|m| m.len()).unwrap_or(0),
+                }
+            })
+            .collect();
+        entries.sort_by(|a, b| a.name.cmp(&b.name));
+
+        lua.to_value(&entries)
+    })?)?;
+
+    module.set("exists", lua.create_function(|lua, path: String| {
+        Ok(path_arg(lua, &path)?.exists())
+    })?)?;
+
+    module.set("mkdir", lua.create_function(|lua, path: String| {
+        let full = path_arg(lua, &path)?;
+        change(lua, "fs.mkdir", path, move || fs::create_dir_all(&full))
+    })?)?;
+
+    module.set("copy", lua.create_function(|lua, (from, to): (String, String)| {
+        let (source, target) = (path_arg(lua, &from)?, path_arg(lua, &to)?);
+        change(lua, "fs.copy", format!("{from} -> {to}"), move || copy_recursive(&source, &target))
+    })?)?;
+
+    module.set("move", lua.create_function(|lua, (from, to): (String, String)| {
+        let (source, target) = (path_arg(lua, &from)?, path_arg(lua, &to)?);
+        change(lua, "fs.move", format!("{from} -> {to}"), move || move_path(&source, &target))
+    })?)?;
+
+    module.set("remove", lua.create_function(|lua, path: String| {
+        let full = path_arg(lua, &path)?;
+
+        change(lua, "fs.remove", path, move || {
+            if full.is_dir() {
+                fs::remove_dir_all(&full)
+            } else {
+                fs::remove_file(&full)
+            }
+        })
+    })?)?;
+
+    module.set("watch", lua.create_async_function(|lua, (path, timeout): (String, Option<u64>)| async move {
+        let full = path_arg(&lua, &path)?;
+        let os = os(&lua);
+        let before = signature(&full);
+        let mut waited = 0;
+
+        while timeout.is_none_or(|timeout| waited < timeout) {
+            os.sleep(Duration::from_millis(WATCH_INTERVAL)).await?;
+            waited += WATCH_INTERVAL;
+
+            if signature(&full) != before {
+                os.note("fs.watch", &format!("{path} -> changed"));
+                return Ok(true);
+            }
+        }
+
+        os.note("fs.watch", &format!("{path} -> timed out"));
+        Ok(false)
+    })?)?;
+
+    lua.globals().set("fs", module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_utils::{get_custom_lua, set_game};
+
+    #[test]
+    fn game_comes_from_rust() {
+        let lua = get_custom_lua();
+        set_game(&lua, "Celeste").unwrap();
+        lua.load(r#"game = "..""#).exec().unwrap();
+
+        assert_eq!(current_game(&lua).unwrap().as_deref(), Some("Celeste"));
+
+        set_game(&lua, "..").unwrap();
+        assert!(current_game(&lua).is_err());
+        assert!(get_data_dir("..").is_none());
+        assert!(resolve(Some(".."), "settings.json").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn dangling_links_dont_resolve() {
+        let dir = std::env::temp_dir().join(format!("luauncher-links-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let link = dir.join("link");
+        let _ = fs::remove_file(&link);
+        std::os::unix::fs::symlink(dir.join("nowhere"), &link).unwrap();
+
+        assert!(real_path(&link.join("save.dat")).is_err());
+        assert!(real_path(&link).is_err());
+        assert!(real_path(&dir.join("missing").join("save.dat")).unwrap().ends_with("missing/save.dat"));
+
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,471 @@
+use mlua::prelude::*;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+use crate::files::{self, get_scripts_dir};
+use crate::lua_utils::{get_custom_lua_with, script_path, set_game};
+use crate::simulate::{self, MockOs, Scenario, TraceEntry};
+use crate::tasks;
+
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct TestReport {
+    pub(crate) name: String,
+    pub(crate) passed: bool,
+    pub(crate) failures: Vec<String>,
+    pub(crate) error: Option<String>,
+    pub(crate) trace: Vec<TraceEntry>,
+}
+
+pub(crate) fn get_tests_dir() -> Option<std::path::PathBuf> {
+    get_scripts_dir().map(|dir| dir.join("tests"))
+}
+
+fn current_line(lua: &Lua) -> String {
+    lua.inspect_stack(1)
+        .map(|debug| format!("line {}: ", debug.curr_line()))
+        .unwrap_or_default()
+}
+
+fn register_expectation<F>(lua: &Lua, failures: &Arc<Mutex<Vec<String>>>, mock: &Arc<MockOs>, name: &str, check: F) -> LuaResult<()>
+where
+    F: Fn(&MockOs, &str) -> Option<String> + Send + 'static,
+{
+    let failures = failures.clone();
+    let mock = mock.clone();
+
+    lua.globals().set(name, lua.create_function(move |lua, target: String| {
+        if let Some(failure) = check(&mock, &target) {
+            failures.lock().unwrap().push(format!("{}{failure}", current_line(lua)));
+        }
+        Ok(())
+    })?)
+}
+
+fn trace_has(mock: &MockOs, action: &str, detail: &str) -> bool {
+    mock.trace().iter().any(|entry| entry.action == action && entry.detail.contains(detail))
+}
+
+/// Adds `scenario`, `runScript`, `runCode` and the `expect*` assertions on top of a Lua state running against `mock`.
+fn install(lua: &Lua, mock: &Arc<MockOs>, failures: &Arc<Mutex<Vec<String>>>) -> LuaResult<()> {
+    let globals = lua.globals();
+
+    let m = mock.clone();
+    globals.set("scenario", lua.create_function(move |lua, table: LuaValue| {
+        let scenario: Scenario = lua.from_value(table)?;
+        m.reset(scenario);
+        Ok(())
+    })?)?;
+
+    for (name, from_file) in [("runScript", true), ("runCode", false)] {
+        globals.set(name, lua.create_async_function(move |lua, source: String| async move {
+            if from_file {
+                set_game(&lua, &source)?;
+            }
+
+            let (code, chunk_name) = if from_file {
+                (files::get_file_content(script_path(&source).to_string_lossy().to_string()), source)
+            } else {
+                (source, "code".to_owned())
+            };
+
+            // Run like a game's script would, as its own task, so tasks it spawns get turns
+            // alongside it instead of waiting for the test to be polled again.
+            let script = lua.clone();
+            let result = tokio::spawn(async move { script.load(code).set_name(chunk_name).exec_async().await })
+                .await
+                .map_err(LuaError::external)?;
+            tasks::cancel_all(&lua);
+
+            match result {
+                Ok(()) => Ok(None),
+                Err(e) => match simulate::exit_code(&e) {
+                    Some(code) => Ok(Some(code)),
+                    None => Err(e),
+                },
+            }
+        })?)?;
+    }
+
+    register_expectation(lua, failures, mock, "expectOpened", |mock, name| {
+        (!mock.was_opened(name)).then(|| format!("expected '{name}' to be opened"))
+    })?;
+    register_expectation(lua, failures, mock, "expectNotOpened", |mock, name| {
+        mock.was_opened(name).then(|| format!("expected '{name}' not to be opened"))
+    })?;
+    register_expectation(lua, failures, mock, "expectClosed", |mock, name| {
+        (!mock.was_closed(name)).then(|| format!("expected '{name}' to be closed"))
+    })?;
+    register_expectation(lua, failures, mock, "expectRunning", |mock, name| {
+        (!mock.is_open(name)).then(|| format!("expected '{name}' to be running"))
+    })?;
+    register_expectation(lua, failures, mock, "expectCommand", |mock, cmd| {
+        (!trace_has(mock, "runCommand", cmd)).then(|| format!("expected command '{cmd}' to be run"))
+    })?;
+    register_expectation(lua, failures, mock, "expectMessage", |mock, text| {
+        (!trace_has(mock, "messageBox", text)).then(|| format!("expected a message box containing '{text}'"))
+    })?;
+    register_expectation(lua, failures, mock, "expectLog", |mock, text| {
+        (!trace_has(mock, "log", text)).then(|| format!("expected a log containing '{text}'"))
+    })?;
+    register_expectation(lua, failures, mock, "expectNotification", |mock, text| {
+        (!trace_has(mock, "notify", text)).then(|| format!("expected a notification containing '{text}'"))
+    })?;
+    register_expectation(lua, failures, mock, "expectProgress", |mock, text| {
+        (!trace_has(mock, "progress", text)).then(|| format!("expected progress containing '{text}'"))
+    })?;
+    register_expectation(lua, failures, mock, "expectStatus", |mock, text| {
+        (!trace_has(mock, "status", text)).then(|| format!("expected a status containing '{text}'"))
+    })?;
+
+    let f = failures.clone();
+    globals.set("expect", lua.create_function(move |lua, (condition, message): (bool, Option<String>)| {
+        if !condition {
+            f.lock().unwrap().push(format!("{}{}", current_line(lua), message.unwrap_or("expectation failed".to_owned())));
+        }
+        Ok(())
+    })?)?;
+
+    Ok(())
+}
+
+/// Runs a test script against a fresh `MockOs`. The script sets up a `scenario`, runs a
+/// game's script with `runScript` (or a snippet with `runCode`) and then checks what happened.
+pub(crate) async fn run_test_code(name: &str, code: &str) -> TestReport {
+    let mock = Arc::new(MockOs::new(Scenario::default()));
+    let failures = Arc::new(Mutex::new(Vec::new()));
+
+    let lua = get_custom_lua_with(mock.clone());
+
+    let error = match install(&lua, &mock, &failures) {
+        Ok(()) => lua.load(code).set_name(name).exec_async().await
+            .err()
+            .filter(|e| simulate::exit_code(e).is_none())
+            .map(|e| e.to_string()),
+        Err(e) => Some(e.to_string()),
+    };
+
+    let failures = failures.lock().unwrap().clone();
+
+    TestReport {
+        name: name.to_owned(),
+        passed: failures.is_empty() && error.is_none(),
+        failures,
+        error,
+        trace: mock.trace(),
+    }
+}
+
+/// Runs every `.lua` file in the tests folder, or only those whose name contains `filter`.
+pub(crate) async fn run_tests(filter: Option<&str>) -> Result<Vec<TestReport>, String> {
+    let dir = get_tests_dir().ok_or("Failed to get tests directory")?;
+
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut paths: Vec<_> = std::fs::read_dir(&dir)
+        .map_err(|e| e.to_string())?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("lua"))
+        .filter(|path| filter.is_none_or(|f| path.to_string_lossy().contains(f)))
+        .collect();
+    paths.sort();
+
+    let mut reports = Vec::new();
+
+    for path in paths {
+        let name = Path::new(&path).file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        let code = files::get_file_content(path.to_string_lossy().to_string());
+
+        reports.push(run_test_code(&name, &code).await);
+    }
+
+    Ok(reports)
+}
+
+/// What `--test` prints and the code it exits with. Split from printing so a running
+/// launcher can send it back to the `--test` that was forwarded to it.
+pub(crate) async fn run_tests_lines(filter: Option<&str>) -> (Vec<String>, i32) {
+    let reports = match run_tests(filter).await {
+        Ok(reports) => reports,
+        Err(e) => return (vec![format!("Failed to run tests: {e}")], 2),
+    };
+
+    let mut lines = Vec::new();
+    let mut failed = 0;
+
+    for report in &reports {
+        if report.passed {
+            lines.push(format!("PASS {}", report.name));
+            continue;
+        }
+
+        failed += 1;
+        lines.push(format!("FAIL {}", report.name));
+
+        for failure in &report.failures {
+            lines.push(format!("    {failure}"));
+        }
+
+        if let Some(error) = &report.error {
+            lines.push(format!("    error: {error}"));
+        }
+    }
+
+    lines.push(format!("{} passed, {} failed", reports.len() - failed, failed));
+
+    (lines, if failed > 0 { 1 } else { 0 })
+}
+
+/// Entry point for `Luauncher --test [filter]`, which runs without opening a window.
+pub(crate) async fn run_tests_cli(filter: Option<&str>) -> i32 {
+    let (lines, code) = run_tests_lines(filter).await;
+
+    for line in lines {
+        println!("{line}");
+    }
+
+    code
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn assert_passes(code: &str) {
+        let report = run_test_code("test", code).await;
+        assert!(report.passed, "failures: {:?}, error: {:?}, trace: {:?}", report.failures, report.error, report.trace);
+    }
+
+    #[tokio::test]
+    async fn wait_until_app_open_uses_the_scenario_clock() {
+        assert_passes(r#"
+            scenario { opens_after = { steam = 3000 } }
+            runCode([[
+                early = waitUntilAppOpen("steam", 1000)
+                late = waitUntilAppOpen("steam", 5000)
+            ]])
+            expect(early == false, "steam shouldn't be open after 1s")
+            expect(late == true, "steam should open within 5s")
+            expectRunning("steam")
+        "#).await;
+    }
+
+    #[tokio::test]
+    async fn open_and_close_are_recorded() {
+        assert_passes(r#"
+            scenario { running = { "discord" } }
+            runCode([[
+                openApp("/usr/games/game.x86_64", { "--fullscreen" })
+                openURL("steam://rungameid/440")
+                closeApp("discord")
+            ]])
+            expectOpened("game")
+            expectOpened("steam")
+            expectClosed("discord")
+            expectNotOpened("discord")
+        "#).await;
+    }
+
+    #[tokio::test]
+    async fn run_command_needs_dev_mode() {
+        assert_passes(r#"
+            scenario { dev_mode = false }
+            local code = runCode([[runCommand("echo hi")]])
+            expect(code == 99, "script should exit with 99")
+            expectMessage("Developer mod off")
+        "#).await;
+    }
+
+    #[tokio::test]
+    async fn run_command_returns_scenario_output() {
+        assert_passes(r#"
+            scenario { commands = { ["git rev-parse HEAD"] = { stdout = "abc123\n" }, ["false"] = { code = 1 } } }
+            runCode([[
+                head = runCommand({ "git", "rev-parse", "HEAD" }, { cwd = "~/game" })
+                failed = runCommand("false")
+            ]])
+            expect(head.code == 0 and head.stdout == "abc123\n", "argv commands should match the scenario")
+            expect(failed.code == 1 and failed.stderr == "", "the exit code should come from the scenario")
+            expectCommand("git rev-parse")
+        "#).await;
+    }
+
+    #[tokio::test]
+    async fn dialogs_use_scenario_answers_then_defaults() {
+        assert_passes(r#"
+            scenario { answers = { false, "Vulkan" } }
+            runCode([[
+                update = confirm("Update", "Install the latest patch?")
+                renderer = choose("Renderer", { "DirectX", "Vulkan" })
+                name = prompt("Player name", "Guest")
+                save = pickFile({ title = "Save", filters = { { name = "Saves", extensions = { "sav" } } } })
+            ]])
+            expect(update == false and renderer == "Vulkan", "answers should be used in order")
+            expect(name == "Guest" and save == nil, "dialogs without answers should use their defaults")
+        "#).await;
+    }
+
+    #[tokio::test]
+    async fn long_waits_notify_when_they_end() {
+        assert_passes(r#"
+            scenario { opens_after = { steam = 1000, game = 45000 } }
+            runCode([[
+                notify("Backup", "Saves backed up", { urgency = "low" })
+                waitUntilAppOpen("steam", 60000)
+                waitUntilAppOpen("game", 60000)
+            ]])
+            expectNotification("Saves backed up")
+            expectNotification("game is open")
+        "#).await;
+    }
+
+    #[tokio::test]
+    async fn prelude_helpers_wait_and_retry() {
+        assert_passes(r#"
+            scenario { opens_after = { steam = 3000, ["Epic Games"] = 500 } }
+            runCode([[
+                result = retry(function(attempt) tries = attempt return attempt == 2 and "ok" end, { delay = 10 })
+                first = waitAny({ "steam", { window = "Epic Games" } }, 10000)
+                both = waitAll({ "steam", { window = "Epic Games" } }, 10000)
+                launched = launchAndWait("/usr/games/game", { timeout = 5000 })
+                picked = onSystem({ default = "other", [system] = "this" })
+            ]])
+            expect(result == "ok" and tries == 2, "retry should stop once the function works")
+            expect(first.window == "Epic Games" and both, "epic should open first, then both")
+            expect(launched and picked == "this", "the game should open and this system should be picked")
+            expect(not pcall(requireApi, apiVersion + 1), "newer api versions should be an error")
+        "#).await;
+    }
+
+    #[tokio::test]
+    async fn tasks_run_side_by_side() {
+        assert_passes(r#"
+            scenario { running = { "discord", "game" }, closes_after = { discord = 5000, game = 60000 } }
+            runCode([[
+                local discord = task.spawn(function() waitUntilAppClose("discord") return "discord" end)
+                local game = task.spawn(function() waitUntilAppClose("game") return "game" end)
+                winner, name = task.race(discord, game)
+                firstWon = winner == discord
+                a, b = task.join(task.spawn(function() return 1 end), task.spawn(function() return 2 end))
+                task.cancel(game)
+                gameStatus = game:status()
+            ]])
+            expect(firstWon and name == "discord", "discord should close first")
+            expect(a == 1 and b == 2, "join should give back each task's result")
+            expect(gameStatus == "cancelled", "the game task should be cancelled")
+            expectRunning("game")
+        "#).await;
+    }
+
+    #[tokio::test]
+    async fn lookups_take_match_options() {
+        assert_passes(r#"
+            scenario { running = { "steamwebhelper" }, opens_after = { ["Steam - News"] = 2000 } }
+            runCode([[
+                plain = isAppOpen("steam")
+                helper = isAppOpen("steam*", { match = "glob" })
+                news = waitUntilWindowOpen("^steam - \\w+$", 5000, { match = "regex", ignoreCase = true })
+            ]])
+            expect(plain == false, "no process is called exactly steam")
+            expect(helper.name == "steamwebhelper", "the glob should return the helper process")
+            expect(news.title == "Steam - News", "the regex should return the news window")
+        "#).await;
+    }
+
+    #[tokio::test]
+    async fn window_module_finds_and_closes_windows() {
+        assert_passes(r#"
+            scenario { running = { "Steam", "Discord Updater" } }
+            runCode([[
+                local updater = window.find({ title = "discord*", match = "glob", ignoreCase = true })
+                window.move(updater, 10, 20)
+                window.close(updater)
+                window.focus("Steam")
+            ]])
+            expect(window.find("Discord") == nil, "the updater should be closed")
+            expect(#window.list() == 1, "only steam should be left")
+            expectClosed("Discord Updater")
+        "#).await;
+    }
+
+    #[tokio::test]
+    async fn http_uses_scenario_responses_and_needs_the_network_capability() {
+        assert_passes(r#"
+            scenario { http = { ["https://example.com/status"] = { body = '{"online":true}' } } }
+            runCode([[
+                status = http.get("https://example.com/status")
+                missing = http.get("https://example.com/other")
+            ]])
+            expect(status.ok and status.json().online == true, "status should come from the scenario")
+            expect(missing.status == 404, "unknown urls should be a 404")
+
+            scenario { capabilities = {} }
+            local ok = pcall(runCode, [[http.get("https://example.com/status")]])
+            expect(not ok, "http should fail without the network capability")
+        "#).await;
+    }
+
+    #[tokio::test]
+    async fn progress_and_status_are_recorded() {
+        assert_passes(r#"
+            runCode([[
+                progress(0.5, "Downloading")
+                status("Patching")
+            ]])
+            expectProgress("50% Downloading")
+            expectStatus("Patching")
+        "#).await;
+    }
+
+    #[test]
+    fn progress_goes_to_the_game_from_rust() {
+        let mock = Arc::new(MockOs::new(Scenario::default()));
+        let lua = get_custom_lua_with(mock.clone());
+        set_game(&lua, "Celeste").unwrap();
+        lua.load(r#"game = "Other" progress(0.5) status("Patching")"#).exec().unwrap();
+
+        let details: Vec<_> = mock.trace().into_iter().map(|entry| entry.detail).collect();
+        assert_eq!(details, vec!["Celeste: 50%".to_owned(), "Celeste: Patching".to_owned()]);
+    }
+
+    #[test]
+    fn storage_reads_back_what_the_simulation_stored() {
+        let mut scenario = Scenario::default();
+        scenario.storage.insert("Celeste".to_owned(), serde_json::json!({ "runs": 1 }).as_object().unwrap().clone());
+
+        let mock = Arc::new(MockOs::new(scenario));
+        let lua = get_custom_lua_with(mock.clone());
+        set_game(&lua, "Celeste").unwrap();
+
+        let (runs, keys): (i64, Vec<String>) = lua.load(r#"
+            storage.set("runs", storage.get("runs") + 1)
+            storage.set("seen", true)
+            storage.delete("seen")
+            return storage.get("runs"), storage.keys()
+        "#).eval().unwrap();
+
+        assert_eq!(runs, 2);
+        assert_eq!(keys, vec!["runs".to_owned()]);
+    }
+
+    #[tokio::test]
+    async fn waiting_on_something_that_never_closes_fails() {
+        let report = run_test_code("test", r#"
+            scenario { running = { "game" }, time_limit = 10000 }
+            runCode([[waitUntilAppClose("game")]])
+        "#).await;
+
+        assert!(!report.passed);
+        assert!(report.error.unwrap_or_default().contains("time limit"));
+    }
+
+    #[tokio::test]
+    async fn failed_expectations_are_reported_with_lines() {
+        let report = run_test_code("test", "runCode('openApp(\"a\")')\nexpectOpened(\"b\")").await;
+
+        assert_eq!(report.failures, vec!["line 2: expected 'b' to be opened".to_owned()]);
+    }
+}
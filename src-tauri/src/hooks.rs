@@ -0,0 +1,181 @@
+use mlua::prelude::*;
+use serde::Serialize;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::files::{self, get_hooks_dir};
+use crate::lua_utils::{get_custom_lua_with, script_path, set_game};
+use crate::os_layer::OsLayer;
+use crate::output;
+use crate::simulate;
+use crate::tasks;
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum Hook {
+    BeforeLaunch,
+    AfterExit,
+}
+
+impl Hook {
+    fn file_name(&self) -> &'static str {
+        match self {
+            Hook::BeforeLaunch => "before_launch.lua",
+            Hook::AfterExit => "after_exit.lua",
+        }
+    }
+}
+
+/// What happened to a game's script, as handed to `after_exit.lua` in the `session` global.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Session {
+    pub(crate) game: String,
+    /// `"success"`, `"failed"` (an error or a non-zero exit code) or `"cancelled"` by a `before_launch.lua` or the user.
+    pub(crate) outcome: String,
+    pub(crate) exit_code: Option<i32>,
+    pub(crate) error: Option<String>,
+    /// Unix time in seconds.
+    pub(crate) started_at: u64,
+    /// Seconds from the first hook starting to the game's script ending.
+    pub(crate) duration: f64,
+}
+
+/// Global hooks live in `scripts/hooks`, per-game ones in `scripts/hooks/<game>`. The global
+/// `before_launch.lua` runs first and the global `after_exit.lua` last, so they wrap the game's own.
+pub(crate) fn hook_paths(hook: Hook, game: &str) -> Vec<PathBuf> {
+    let Some(dir) = get_hooks_dir() else {
+        return Vec::new();
+    };
+
+    let global = dir.join(hook.file_name());
+    let own = dir.join(game).join(hook.file_name());
+
+    let ordered = match hook {
+        Hook::BeforeLaunch => [global, own],
+        Hook::AfterExit => [own, global],
+    };
+
+    ordered.into_iter().filter(|path| path.is_file()).collect()
+}
+
+enum Ending {
+    Returned(bool),
+    Exited(i32),
+}
+
+async fn run_file(lua: &Lua, path: &Path, name: &str) -> LuaResult<Ending> {
+    let code = files::get_file_content(path.to_string_lossy().to_string());
+
+    let result = lua.load(code).set_name(name).eval_async::<LuaValue>().await;
+
+    // Tasks the script didn't wait for end with it.
+    tasks::cancel_all(lua);
+
+    match result {
+        Ok(value) => Ok(Ending::Returned(!matches!(value, LuaValue::Boolean(false)))),
+        Err(e) => simulate::exit_code(&e).map(Ending::Exited).ok_or(e),
+    }
+}
+
+fn hook_name(path: &Path, game: &str) -> String {
+    let file = path.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+
+    if path.parent().and_then(|p| p.file_name()).is_some_and(|dir| dir == game) {
+        format!("hooks/{game}/{file}")
+    } else {
+        format!("hooks/{file}")
+    }
+}
+
+/// Runs every hook for `hook`. Before launch, a hook that errors, returns `false` or exits with
+/// a non-zero code stops the rest and cancels the launch, which is returned as the reason.
+async fn run_hooks(hook: Hook, game: &str, os_layer: &Arc<dyn OsLayer>, session: Option<&Session>) -> Option<String> {
+    for path in hook_paths(hook, game) {
+        let name = hook_name(&path, game);
+        os_layer.note("hook", &name);
+
+        let lua = get_custom_lua_with(os_layer.clone());
+        let globals = lua.globals();
+        let _ = set_game(&lua, game);
+
+        if let Some(session) = session {
+            match lua.to_value(session) {
+                Ok(value) => {
+                    let _ = globals.set("session", value);
+                }
+                Err(e) => output::add_log(format!("[Hooks] Failed to pass the session to {name}: {e}"), output::LogLevel::Error, false),
+            }
+        }
+
+        let reason = match run_file(&lua, &path, &name).await {
+            Ok(Ending::Returned(true)) | Ok(Ending::Exited(0)) => None,
+            Ok(Ending::Returned(false)) => Some(format!("{name} returned false")),
+            Ok(Ending::Exited(code)) => Some(format!("{name} exited with code {code}")),
+            Err(e) => Some(format!("{name} failed: {e}")),
+        };
+
+        if let Some(reason) = reason {
+            output::add_log(format!("[Hooks] {reason}"), output::LogLevel::Error, false);
+
+            if hook == Hook::BeforeLaunch {
+                return Some(reason);
+            }
+        }
+    }
+
+    None
+}
+
+/// Runs a game's script between its `before_launch.lua` and `after_exit.lua` hooks. `os_layer`
+/// must not really exit (see `os_layer::DeferredExit`), otherwise `after_exit.lua` never runs.
+/// The script is stopped if `cancelled` finishes first, but `after_exit.lua` still runs.
+pub(crate) async fn run_with_hooks(game: &str, os_layer: Arc<dyn OsLayer>, cancelled: impl Future<Output = ()>) -> Session {
+    let started = Instant::now();
+
+    let mut session = Session {
+        game: game.to_owned(),
+        outcome: "success".to_owned(),
+        exit_code: None,
+        error: None,
+        started_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        duration: 0.0,
+    };
+
+    if let Some(reason) = run_hooks(Hook::BeforeLaunch, game, &os_layer, None).await {
+        session.outcome = "cancelled".to_owned();
+        session.error = Some(reason);
+    } else {
+        let lua = get_custom_lua_with(os_layer.clone());
+        let _ = set_game(&lua, game);
+
+        let path = script_path(game);
+
+        let ending = tokio::select! {
+            ending = run_file(&lua, &path, game) => Some(ending),
+            _ = cancelled => None,
+        };
+
+        match ending {
+            Some(Ok(Ending::Returned(_))) => session.exit_code = Some(0),
+            Some(Ok(Ending::Exited(code))) => session.exit_code = Some(code),
+            Some(Err(e)) => session.error = Some(e.to_string()),
+            None => {
+                tasks::cancel_all(&lua);
+                session.outcome = "cancelled".to_owned();
+                session.error = Some("It was cancelled".to_owned());
+            }
+        }
+
+        if session.exit_code != Some(0) && session.outcome != "cancelled" {
+            session.outcome = "failed".to_owned();
+        }
+    }
+
+    session.duration = started.elapsed().as_secs_f64();
+
+    run_hooks(Hook::AfterExit, game, &os_layer, Some(&session)).await;
+
+    session
+}
@@ -0,0 +1,422 @@
+use mlua::prelude::*;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+use crate::fs_api;
+use crate::lua_utils::current_game;
+use crate::os_layer::os;
+
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Capability a script needs in `Settings.capabilities` to use `http`.
+pub(crate) const NETWORK: &str = "network";
+
+#[derive(Clone, Debug)]
+pub(crate) struct HttpRequest {
+    pub(crate) method: String,
+    pub(crate) url: String,
+    pub(crate) headers: Vec<(String, String)>,
+    pub(crate) body: Option<Vec<u8>>,
+    pub(crate) timeout: Duration,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct HttpResponse {
+    pub(crate) status: u16,
+    pub(crate) headers: HashMap<String, String>,
+    pub(crate) body: Vec<u8>,
+}
+
+pub(crate) type Progress<'a> = Box<dyn FnMut(u64, Option<u64>) -> LuaResult<()> + Send + 'a>;
+
+/// `request.timeout` limits connecting and each wait for the server to send something, not the
+/// whole transfer, so a download that keeps coming in isn't cut off however long it takes.
+fn build(request: &HttpRequest) -> Result<reqwest::RequestBuilder, String> {
+    let method = reqwest::Method::from_bytes(request.method.to_uppercase().as_bytes())
+        .map_err(|_| format!("'{}' isn't an HTTP method", request.method))?;
+
+    let client = reqwest::Client::builder()
+        .connect_timeout(request.timeout)
+        .read_timeout(request.timeout)
+        .build()
+        .map_err(|e| format!("Failed to set up the request to {}: {e}", request.url))?;
+
+    let mut builder = client
+        .request(method, &request.url)
+        .header("User-Agent", "Luauncher-app");
+
+    for (name, value) in &request.headers {
+        builder = builder.header(name, value);
+    }
+
+    if let Some(body) = &request.body {
+        builder = builder.body(body.clone());
+    }
+
+    Ok(builder)
+}
+
+pub(crate) async fn send(request: HttpRequest) -> Result<HttpResponse, String> {
+    let response = build(&request)?
+        .send()
+        .await
+        .map_err(|e| format!("Request to {} failed: {e}", request.url))?;
+
+    let status = response.status().as_u16();
+    let headers = response.headers()
+        .iter()
+        .map(|(name, value)| (name.to_string(), value.to_str().unwrap_or_default().to_owned()))
+        .collect();
+
+    let body = response.bytes()
+        .await
+        .map_err(|e| format!("Failed to read the response from {}: {e}", request.url))?
+        .to_vec();
+
+    Ok(HttpResponse { status, headers, body })
+}
+
+/// Streams the response into `path`, calling `progress` with the bytes so far and the total if the
+/// server sent one. The file is written next to `path` first and only renamed once it's complete.
+/// The file a download is written to until it's complete. It's removed if the download fails
+/// or is cancelled part way, so a half-written file isn't left next to the real one.
+struct PartFile(Option<PathBuf>);
+
+impl PartFile {
+    /// `game.zip` downloads to `game.zip.part`, so files that only differ in extension don't share one.
+    fn for_path(path: &Path) -> Self {
+        let mut name = path.as_os_str().to_owned();
+        name.push(".part");
+        PartFile(Some(PathBuf::from(name)))
+    }
+
+    fn path(&self) -> &Path {
+        self.0.as_deref().unwrap_or(Path::new(""))
+    }
+
+    fn keep(mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for PartFile {
+    fn drop(&mut self) {
+        if let Some(path) = self.0.take() {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+pub(crate) async fn download(request: HttpRequest, path: &Path, mut progress: Progress<'_>) -> LuaResult<u64> {
+    let mut response = build(&request)
+        .map_err(LuaError::external)?
+        .send()
+        .await
+        .and_then(|response| response.error_for_status())
+        .map_err(|e| LuaError::external(format!("Download of {} failed: {e}", request.url)))?;
+
+    let total = response.content_length();
+
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(LuaError::external)?;
+    }
+
+    let temp = PartFile::for_path(path);
+    let mut file = tokio::fs::File::create(temp.path()).await.map_err(LuaError::external)?;
+    let mut done = 0;
+
+    progress(0, total)?;
+
+    while let Some(chunk) = response.chunk()
+        .await
+        .map_err(|e| LuaError::external(format!("Download of {} failed: {e}", request.url)))?
+    {
+        file.write_all(&chunk).await.map_err(LuaError::external)?;
+        done += chunk.len() as u64;
+        progress(done, total)?;
+    }
+
+    file.flush().await.map_err(LuaError::external)?;
+    drop(file);
+
+    tokio::fs::rename(temp.path(), path).await.map_err(LuaError::external)?;
+    temp.keep();
+
+    Ok(done)
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct RequestOptions {
+    headers: HashMap<String, String>,
+    /// In milliseconds.
+    timeout: Option<u64>,
+}
+
+fn request_from(method: &str, url: String, options: RequestOptions) -> HttpRequest {
+    HttpRequest {
+        method: method.to_owned(),
+        url,
+        headers: options.headers.into_iter().collect(),
+        body: None,
+        timeout: options.timeout.map(Duration::from_millis).unwrap_or(DEFAULT_TIMEOUT),
+    }
+}
+
+fn options(lua: &Lua, value: Option<LuaValue>) -> LuaResult<RequestOptions> {
+    match value {
+        Some(value) if !value.is_nil() => lua.from_value(value),
+        _ => Ok(RequestOptions::default()),
+    }
+}
+
+/// Tables are sent as JSON, anything else as it is.
+fn set_body(lua: &Lua, request: &mut HttpRequest, body: LuaValue) -> LuaResult<()> {
+    match body {
+        LuaValue::Nil => {}
+        LuaValue::String(text) => request.body = Some(text.as_bytes().to_vec()),
+        LuaValue::Table(_) => {
+            let json: serde_json::Value = lua.from_value(body)?;
+            request.body = Some(json.to_string().into_bytes());
+
+            if !request.headers.iter().any(|(name, _)| name.eq_ignore_ascii_case("content-type")) {
+                request.headers.push(("Content-Type".to_owned(), "application/json".to_owned()));
+            }
+        }
+        other => request.body = Some(other.to_string()?.into_bytes()),
+    }
+
+    Ok(())
+}
+
+fn require_network(lua: &Lua) -> LuaResult<()> {
+    let game = current_game(lua)?;
+
+    if os(lua).has_capability(game.as_deref(), NETWORK)? {
+        return Ok(());
+    }
+
+    Err(LuaError::external(format!(
+        "'{}' isn't allowed to use the network, turn on the \"{NETWORK}\" capability for it first",
+        game.unwrap_or("This script".to_owned())
+    )))
+}
+
+/// Turns a response into `{ status, ok, headers, body }` plus a `json()` that decodes the body.
+fn response_table(lua: &Lua, response: HttpResponse) -> LuaResult<LuaTable> {
+    let table = lua.create_table()?;
+
+    table.set("status", response.status)?;
+    table.set("ok", (200..300).contains(&response.status))?;
+    table.set("headers", response.headers)?;
+    table.set("body", lua.create_string(&response.body)?)?;
+
+    let body = response.body;
+    table.set("json", lua.create_function(move |lua, _: LuaMultiValue| {
+        let json: serde_json::Value = serde_json::from_slice(&body)
+            .map_err(|e| LuaError::external(format!("Response isn't JSON: {e}")))?;
+
+        lua.to_value(&json)
+    })?)?;
+
+    Ok(table)
+}
+
+async fn perform(lua: &Lua, request: HttpRequest) -> LuaResult<LuaTable> {
+    require_network(lua)?;
+
+    let response = os(lua).http(request).await?;
+    response_table(lua, response)
+}
+
+/// Builds the `http` table. Every call needs the network capability.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    let module = lua.create_table()?;
+
+    module.set("get", lua.create_async_function(|lua, (url, opts): (String, Option<LuaValue>)| async move {
+        let request = request_from("GET", url, options(&lua, opts)?);
+        perform(&lua, request).await
+    })?)?;
+
+    module.set("post", lua.create_async_function(|lua, (url, body, opts): (String, LuaValue, Option<LuaValue>)| async move {
+        let mut request = request_from("POST", url, options(&lua, opts)?);
+        set_body(&lua, &mut request, body)?;
+        perform(&lua, request).await
+    })?)?;
+
+    module.set("request", lua.create_async_function(|lua, table: LuaTable| async move {
+        let method = table.get::<Option<String>>("method")?.unwrap_or("GET".to_owned());
+        let url = table.get::<String>("url")?;
+        let body = table.get::<LuaValue>("body")?;
+
+        let mut request = request_from(&method, url, lua.from_value(LuaValue::Table(table))?);
+        set_body(&lua, &mut request, body)?;
+        perform(&lua, request).await
+    })?)?;
+
+    module.set("download", lua.create_async_function(|lua, (url, path, opts): (String, String, Option<LuaTable>)| async move {
+        require_network(&lua)?;
+
+        let game = current_game(&lua)?;
+        let target: PathBuf = fs_api::resolve(game.as_deref(), &path).map_err(LuaError::external)?;
+
+        let callback = opts.as_ref().map(|o| o.get::<Option<LuaFunction>>("progress")).transpose()?.flatten();
+        let request = request_from("GET", url, match opts {
+            Some(table) => lua.from_value(LuaValue::Table(table))?,
+            None => RequestOptions::default(),
+        });
+
+        let progress: Progress = Box::new(move |done, total| match &callback {
+            Some(callback) => callback.call::<()>((done, total)),
+            None => Ok(()),
+        });
+
+        os(&lua).download(request, target, progress).await
+    })?)?;
+
+    lua.globals().set("http", module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{download, send, HttpRequest, DEFAULT_TIMEOUT};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+
+    /// Serves one canned response per connection and hands back the requests it got.
+    fn mock_server(responses: Vec<String>) -> (String, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let seen = requests.clone();
+
+        std::thread::spawn(move || {
+            for response in responses {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut buffer = [0u8; 4096];
+                let read = stream.read(&mut buffer).unwrap();
+
+                seen.lock().unwrap().push(String::from_utf8_lossy(&buffer[..read]).to_string());
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+
+        (url, requests)
+    }
+
+    fn request(method: &str, url: String) -> HttpRequest {
+        HttpRequest { method: method.to_owned(), url, headers: Vec::new(), body: None, timeout: DEFAULT_TIMEOUT }
+    }
+
+    #[tokio::test]
+    async fn sends_headers_and_body_and_reads_the_response() {
+        let (url, requests) = mock_server(vec![
+            "HTTP/1.1 201 Created\r\nContent-Type: application/json\r\nContent-Length: 11\r\nConnection: close\r\n\r\n{\"up\":true}".to_owned(),
+        ]);
+
+        let mut post = request("POST", format!("{url}/status"));
+        post.headers.push(("X-Token".to_owned(), "abc".to_owned()));
+        post.body = Some(b"ping".to_vec());
+
+        let response = send(post).await.unwrap();
+
+        assert_eq!(response.status, 201);
+        assert_eq!(response.headers.get("content-type").map(String::as_str), Some("application/json"));
+        assert_eq!(response.body, b"{\"up\":true}");
+
+        let raw = requests.lock().unwrap()[0].to_lowercase();
+        assert!(raw.starts_with("post /status"));
+        assert!(raw.contains("x-token: abc"));
+        assert!(raw.ends_with("ping"));
+    }
+
+    #[tokio::test]
+    async fn downloads_to_a_file_with_progress() {
+        let (url, _) = mock_server(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 5\r\nConnection: close\r\n\r\nhello".to_owned(),
+        ]);
+
+        let path = std::env::temp_dir().join(format!("luauncher-download-{}", std::process::id()));
+        let mut seen = Vec::new();
+
+        let size = download(request("GET", url), &path, Box::new(|done, total| {
+            seen.push((done, total));
+            Ok(())
+        })).await.unwrap();
+
+        assert_eq!(size, 5);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "hello");
+        assert_eq!(seen.first(), Some(&(0, Some(5))));
+        assert_eq!(seen.last(), Some(&(5, Some(5))));
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn failed_downloads_leave_no_part_file() {
+        let (url, _) = mock_server(vec![
+            "HTTP/1.1 200 OK\r\nContent-Length: 10\r\nConnection: close\r\n\r\nhello".to_owned(),
+        ]);
+
+        let path = std::env::temp_dir().join(format!("luauncher-cut-download-{}.zip", std::process::id()));
+
+        assert!(download(request("GET", url), &path, Box::new(|_, _| Ok(()))).await.is_err());
+        assert!(!path.exists());
+        assert!(!std::path::Path::new(&format!("{}.part", path.display())).exists());
+    }
+
+    /// Sends the response a piece at a time, `pause` apart.
+    fn slow_server(pieces: Vec<&'static str>, pause: std::time::Duration) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let _ = stream.read(&mut [0u8; 4096]).unwrap();
+
+            for piece in pieces {
+                stream.write_all(piece.as_bytes()).unwrap();
+                stream.flush().unwrap();
+                std::thread::sleep(pause);
+            }
+        });
+
+        url
+    }
+
+    #[tokio::test]
+    async fn timeout_is_between_reads_not_for_the_whole_download() {
+        let url = slow_server(
+            vec!["HTTP/1.1 200 OK\r\nContent-Length: 4\r\nConnection: close\r\n\r\n", "a", "b", "c", "d"],
+            std::time::Duration::from_millis(100),
+        );
+
+        let path = std::env::temp_dir().join(format!("luauncher-slow-download-{}", std::process::id()));
+        let mut slow = request("GET", url);
+        slow.timeout = std::time::Duration::from_millis(300);
+
+        // Takes about 500 ms in all, longer than the timeout, but no single wait does.
+        let size = download(slow, &path, Box::new(|_, _| Ok(()))).await.unwrap();
+
+        assert_eq!(size, 4);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "abcd");
+
+        let _ = std::fs::remove_file(path);
+    }
+
+    #[tokio::test]
+    async fn times_out() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+
+        let mut slow = request("GET", url);
+        slow.timeout = std::time::Duration::from_millis(200);
+
+        assert!(send(slow).await.is_err());
+        drop(listener);
+    }
+}
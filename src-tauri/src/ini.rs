@@ -0,0 +1,343 @@
+use mlua::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+
+use crate::fs_api;
+
+#[derive(Clone, Debug)]
+enum Line {
+    /// Blank lines, comments and anything else that isn't understood, kept exactly as they were.
+    Other(String),
+    Section { name: String, raw: String },
+    /// `raw` is the original line, dropped once the value changes.
+    Entry { key: String, value: String, separator: String, raw: Option<String> },
+}
+
+/// An INI file as a list of lines, so comments, order and formatting survive being edited.
+/// Sections and keys are matched ignoring case, like Windows does. Keys before the first
+/// section are in the section `""`.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct IniDoc {
+    lines: Vec<Line>,
+    newline: &'static str,
+    final_newline: bool,
+    /// Where the document was loaded from, which `save()` writes back to.
+    path: Option<(String, PathBuf)>,
+}
+
+fn same(a: &str, b: &str) -> bool {
+    a.eq_ignore_ascii_case(b)
+}
+
+impl IniDoc {
+    pub(crate) fn parse(text: &str) -> Self {
+        let newline = if text.contains("\r\n") { "\r\n" } else { "\n" };
+
+        let lines = text.lines()
+            .map(|line| {
+                let trimmed = line.trim();
+
+                if trimmed.is_empty() || trimmed.starts_with(';') || trimmed.starts_with('#') {
+                    return Line::Other(line.to_owned());
+                }
+
+                if let Some(name) = trimmed.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+                    return Line::Section { name: name.trim().to_owned(), raw: line.to_owned() };
+                }
+
+                match line.split_once('=') {
+                    Some((key, value)) => {
+                        let spaced = key.ends_with(' ') || value.starts_with(' ');
+
+                        Line::Entry {
+                            key: key.trim().to_owned(),
+                            value: value.trim().to_owned(),
+                            separator: if spaced { " = " } else { "=" }.to_owned(),
+                            raw: Some(line.to_owned()),
+                        }
+                    }
+                    None => Line::Other(line.to_owned()),
+                }
+            })
+            .collect();
+
+        IniDoc { lines, newline, final_newline: text.ends_with('\n'), path: None }
+    }
+
+    /// The range of lines in `section`, not counting its header. `None` if it isn't there.
+    fn section_range(&self, section: &str) -> Option<(usize, usize)> {
+        let start = if section.is_empty() {
+            0
+        } else {
+            self.lines.iter().position(|line| matches!(line, Line::Section { name, .. } if same(name, section)))? + 1
+        };
+
+        let end = self.lines[start..].iter()
+            .position(|line| matches!(line, Line::Section { .. }))
+            .map(|offset| start + offset)
+            .unwrap_or(self.lines.len());
+
+        Some((start, end))
+    }
+
+    fn find(&self, section: &str, key: &str) -> Option<usize> {
+        let (start, end) = self.section_range(section)?;
+
+        (start..end).find(|&i| matches!(&self.lines[i], Line::Entry { key: k, .. } if same(k, key)))
+    }
+
+    pub(crate) fn get(&self, section: &str, key: &str) -> Option<&str> {
+        match &self.lines[self.find(section, key)?] {
+            Line::Entry { value, .. } => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Changes the value in place, or adds the key after the last one in its section, making the
+    /// section at the end of the file if it doesn't exist yet.
+    pub(crate) fn set(&mut self, section: &str, key: &str, value: String) {
+        if let Some(i) = self.find(section, key) {
+            if let Line::Entry { value: old, raw, .. } = &mut self.lines[i] {
+                if *old != value {
+                    *old = value;
+                    *raw = None;
+                }
+            }
+            return;
+        }
+
+        // New keys copy the spacing the file already uses.
+        let separator = self.lines.iter()
+            .find_map(|line| match line {
+                Line::Entry { separator, .. } => Some(separator.clone()),
+                _ => None,
+            })
+            .unwrap_or("=".to_owned());
+
+        let entry = Line::Entry { key: key.to_owned(), value, separator, raw: None };
+
+        match self.section_range(section) {
+            Some((start, end)) => {
+                // Comments after the last key usually belong to the next section, so they stay below it.
+                let at = (start..end).rev()
+                    .find(|&i| matches!(self.lines[i], Line::Entry { .. }))
+                    .map(|i| i + 1)
+                    .unwrap_or(start);
+                self.lines.insert(at, entry);
+            }
+            None => {
+                if self.lines.last().is_some_and(|line| !matches!(line, Line::Other(text) if text.trim().is_empty())) {
+                    self.lines.push(Line::Other(String::new()));
+                }
+
+                self.lines.push(Line::Section { name: section.to_owned(), raw: format!("[{section}]") });
+                self.lines.push(entry);
+            }
+        }
+    }
+
+    /// Removes one key, or a whole section (header included) when `key` is `None`.
+    pub(crate) fn remove(&mut self, section: &str, key: Option<&str>) -> bool {
+        match key {
+            Some(key) => match self.find(section, key) {
+                Some(i) => {
+                    self.lines.remove(i);
+                    true
+                }
+                None => false,
+            },
+            None => match self.section_range(section) {
+                Some((start, end)) => {
+                    self.lines.drain(start.saturating_sub(if section.is_empty() { 0 } else { 1 })..end);
+                    true
+                }
+                None => false,
+            },
+        }
+    }
+
+    pub(crate) fn sections(&self) -> Vec<String> {
+        let global = self.section_range("").is_some_and(|(start, end)| {
+            self.lines[start..end].iter().any(|line| matches!(line, Line::Entry { .. }))
+        });
+
+        global.then(String::new).into_iter()
+            .chain(self.lines.iter().filter_map(|line| match line {
+                Line::Section { name, .. } => Some(name.clone()),
+                _ => None,
+            }))
+            .collect()
+    }
+
+    pub(crate) fn entries(&self, section: &str) -> Vec<(String, String)> {
+        let Some((start, end)) = self.section_range(section) else {
+            return Vec::new();
+        };
+
+        self.lines[start..end].iter()
+            .filter_map(|line| match line {
+                Line::Entry { key, value, .. } => Some((key.clone(), value.clone())),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub(crate) fn render(&self) -> String {
+        let mut text = self.lines.iter()
+            .map(|line| match line {
+                Line::Other(raw) | Line::Section { raw, .. } => raw.clone(),
+                Line::Entry { raw: Some(raw), .. } => raw.clone(),
+                Line::Entry { key, value, separator, raw: None } => format!("{key}{separator}{value}"),
+            })
+            .collect::<Vec<_>>()
+            .join(self.newline);
+
+        if self.final_newline && !self.lines.is_empty() {
+            text.push_str(self.newline);
+        }
+
+        text
+    }
+}
+
+fn to_ini_value(value: LuaValue) -> LuaResult<String> {
+    match value {
+        LuaValue::String(text) => Ok(text.to_str()?.to_owned()),
+        LuaValue::Boolean(_) | LuaValue::Integer(_) | LuaValue::Number(_) => value.to_string(),
+        other => Err(LuaError::external(format!("INI values can only be strings, numbers or bools, not {}", other.type_name()))),
+    }
+}
+
+impl LuaUserData for IniDoc {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_method("get", |_, this, (section, key, default): (String, String, Option<String>)| {
+            Ok(this.get(&section, &key).map(str::to_owned).or(default))
+        });
+
+        methods.add_method_mut("set", |_, this, (section, key, value): (String, String, LuaValue)| {
+            match value {
+                LuaValue::Nil => {
+                    this.remove(&section, Some(&key));
+                }
+                value => this.set(&section, &key, to_ini_value(value)?),
+            }
+            Ok(())
+        });
+
+        methods.add_method_mut("remove", |_, this, (section, key): (String, Option<String>)| {
+            Ok(this.remove(&section, key.as_deref()))
+        });
+
+        methods.add_method("sections", |_, this, ()| Ok(this.sections()));
+
+        methods.add_method("keys", |_, this, section: String| {
+            Ok(this.entries(&section).into_iter().map(|(key, _)| key).collect::<Vec<_>>())
+        });
+
+        methods.add_method("toString", |_, this, ()| Ok(this.render()));
+
+        methods.add_method("save", |lua, this, path: Option<String>| {
+            let (name, full) = match path {
+                Some(path) => (path.clone(), fs_api::path_arg(lua, &path)?),
+                None => this.path.clone()
+                    .ok_or_else(|| LuaError::external("This INI wasn't loaded from a file, send a path to save it to"))?,
+            };
+
+            let text = this.render();
+
+            fs_api::change(lua, "ini.save", name, move || {
+                if let Some(parent) = full.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::write(&full, text)
+            })
+        });
+
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| Ok(this.render()));
+    }
+}
+
+/// Builds the `ini` table. `parse` and `load` give a document that keeps comments and order,
+/// `decode` and `encode` convert between INI text and plain `{ section = { key = value } }` tables.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    let module = lua.create_table()?;
+
+    module.set("parse", lua.create_function(|_, text: String| Ok(IniDoc::parse(&text)))?)?;
+
+    module.set("load", lua.create_function(|lua, path: String| {
+        let full = fs_api::path_arg(lua, &path)?;
+        let text = fs::read_to_string(&full)
+            .map_err(|e| LuaError::external(format!("Failed to read '{path}': {e}")))?;
+
+        let mut doc = IniDoc::parse(&text);
+        doc.path = Some((path, full));
+        Ok(doc)
+    })?)?;
+
+    module.set("decode", lua.create_function(|lua, text: String| {
+        let doc = IniDoc::parse(&text);
+        let result = lua.create_table()?;
+
+        for section in doc.sections() {
+            let table = lua.create_table()?;
+
+            // `get` sees the first of a duplicated key, so they're set last to first for the first to win.
+            for (key, value) in doc.entries(&section).into_iter().rev() {
+                table.set(key, value)?;
+            }
+
+            result.set(section, table)?;
+        }
+
+        Ok(result)
+    })?)?;
+
+    module.set("encode", lua.create_function(|_, table: LuaTable| {
+        let mut doc = IniDoc { newline: "\n", final_newline: true, ..Default::default() };
+
+        let mut sections: Vec<(String, LuaTable)> = table.pairs::<String, LuaTable>().collect::<LuaResult<_>>()?;
+        // Keys before the first section only work at the top, and the rest are sorted so the output is stable.
+        sections.sort_by(|a, b| (!a.0.is_empty(), &a.0).cmp(&(!b.0.is_empty(), &b.0)));
+
+        for (section, entries) in sections {
+            let mut entries: Vec<(String, LuaValue)> = entries.pairs::<String, LuaValue>().collect::<LuaResult<_>>()?;
+            entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+            for (key, value) in entries {
+                doc.set(&section, &key, to_ini_value(value)?);
+            }
+        }
+
+        Ok(doc.render())
+    })?)?;
+
+    lua.globals().set("ini", module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IniDoc;
+
+    const CONFIG: &str = "; Game settings\r\n[Display]\r\nWidth = 1920\r\nHeight = 1080 \r\n\r\n# Audio\r\n[Audio]\r\nVolume = 80\r\n";
+
+    #[test]
+    fn untouched_files_come_back_the_same() {
+        assert_eq!(IniDoc::parse(CONFIG).render(), CONFIG);
+    }
+
+    #[test]
+    fn edits_keep_comments_order_and_line_endings() {
+        let mut doc = IniDoc::parse(CONFIG);
+
+        doc.set("display", "width", "2560".to_owned());
+        doc.set("Display", "Fullscreen", "true".to_owned());
+        doc.set("Network", "Port", "7777".to_owned());
+        doc.remove("Audio", Some("Volume"));
+
+        assert_eq!(doc.get("DISPLAY", "WIDTH"), Some("2560"));
+        assert_eq!(
+            doc.render(),
+            "; Game settings\r\n[Display]\r\nWidth = 2560\r\nHeight = 1080 \r\nFullscreen = true\r\n\r\n# Audio\r\n[Audio]\r\n\r\n[Network]\r\nPort = 7777\r\n"
+        );
+    }
+}
@@ -0,0 +1,323 @@
+use once_cell::sync::Lazy;
+use serde::{Serialize, Deserialize};
+use std::fs::{self, File, TryLockError};
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::tcp::OwnedWriteHalf;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::watch;
+use tokio::time::{sleep, timeout, Instant};
+use uuid::Uuid;
+
+use crate::files::get_app_base;
+use crate::harness;
+use crate::lua_utils;
+use crate::output;
+use crate::tray;
+
+/// How long a second launch waits to hear from the running one before deciding it's gone.
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often a second launch looks for the running one's port while it's starting up.
+const RETRY_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Sent as soon as a request is let in, before it's run, since running can take as long as the script does.
+const ACCEPTED: &str = "accepted";
+
+/// A second launch's arguments, with the token from the instance file to show it's the same user.
+#[derive(Serialize, Deserialize)]
+struct Request {
+    token: String,
+    args: Vec<String>,
+}
+
+/// What the running launcher did with forwarded arguments, for the second launch to print and exit with.
+#[derive(Serialize, Deserialize, Debug, Default)]
+pub(crate) struct Reply {
+    pub(crate) code: i32,
+    pub(crate) output: Vec<String>,
+}
+
+impl Reply {
+    pub(crate) fn print(&self) {
+        for line in &self.output {
+            println!("{line}");
+        }
+    }
+}
+
+/// The lock file, held by the launcher that's running for as long as it runs. The OS lets go of
+/// it when the process ends, so a crash can't leave it taken.
+static LOCK: Mutex<Option<File>> = Mutex::new(None);
+
+/// Set when this launcher was started to do one thing without its window, from a shortcut or
+/// with `--test`, and closes once that's done.
+static ONE_OFF: AtomicBool = AtomicBool::new(false);
+
+/// Launches handed over by others that are still running, and whether this launcher is closing.
+#[derive(Default)]
+struct Forwarded {
+    running: usize,
+    closing: bool,
+}
+
+static FORWARDED: Lazy<watch::Sender<Forwarded>> = Lazy::new(|| watch::channel(Forwarded::default()).0);
+
+/// Counts a forwarded launch as running until it's dropped, however it ends.
+struct Admitted;
+
+impl Admitted {
+    /// `None` once the launcher is closing, since it would be cut off.
+    fn new() -> Option<Self> {
+        FORWARDED.send_if_modified(|forwarded| {
+            if forwarded.closing {
+                return false;
+            }
+
+            forwarded.running += 1;
+            true
+        }).then_some(Admitted)
+    }
+}
+
+impl Drop for Admitted {
+    fn drop(&mut self) {
+        FORWARDED.send_modify(|forwarded| forwarded.running -= 1);
+    }
+}
+
+/// Holds the running launcher's port and token, one per line.
+fn instance_path() -> Option<PathBuf> {
+    get_app_base().map(|base| base.join("instance"))
+}
+
+fn lock_path() -> Option<PathBuf> {
+    get_app_base().map(|base| base.join("instance.lock"))
+}
+
+/// Makes this the running launcher, or returns `false` when another one already is. Decided
+/// before anything else happens, so of two launches at the same time only one goes on.
+pub(crate) fn claim() -> bool {
+    let Some(path) = lock_path() else {
+        return true;
+    };
+
+    let file = path.parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| File::options().create(true).truncate(false).write(true).open(&path));
+
+    let result = file.and_then(|file| match file.try_lock() {
+        Ok(()) => Ok(Some(file)),
+        Err(TryLockError::WouldBlock) => Ok(None),
+        Err(TryLockError::Error(e)) => Err(e),
+    });
+
+    match result {
+        Ok(Some(file)) => {
+            *LOCK.lock().unwrap_or_else(|e| e.into_inner()) = Some(file);
+            true
+        }
+        Ok(None) => false,
+        // Without a lock there's nothing to go by, and not starting at all would be worse.
+        Err(e) => {
+            output::add_log(format!("[Single Instance] Failed to lock {}: {e}", path.display()), output::LogLevel::Warning, false);
+            true
+        }
+    }
+}
+
+/// Removes the instance file and lets go of the lock, so the next launch runs on its own.
+pub(crate) fn release() {
+    if LOCK.lock().unwrap_or_else(|e| e.into_inner()).take().is_none() {
+        return;
+    }
+
+    if let Some(path) = instance_path() {
+        let _ = fs::remove_file(path);
+    }
+}
+
+/// Marks this launcher as one that closes once it has done what it was started for.
+pub(crate) fn set_one_off() {
+    ONE_OFF.store(true, Ordering::Relaxed);
+}
+
+/// Stops taking launches from others and waits for the ones it took, so closing doesn't cut
+/// their scripts, hooks and save backups off halfway.
+pub(crate) async fn finish() {
+    FORWARDED.send_modify(|forwarded| forwarded.closing = true);
+    let _ = FORWARDED.subscribe().wait_for(|forwarded| forwarded.running == 0).await;
+}
+
+fn read_instance() -> Option<(u16, String)> {
+    let text = std::fs::read_to_string(instance_path()?).ok()?;
+    let mut lines = text.lines();
+
+    Some((lines.next()?.trim().parse().ok()?, lines.next()?.trim().to_owned()))
+}
+
+/// Does what launching with `args` would: nothing shows the window, `--test` runs the script
+/// tests and anything else is a game's name, like shortcuts pass.
+pub(crate) async fn execute(args: &[String]) -> Reply {
+    match args.first().map(String::as_str) {
+        // A one-off launcher closes when it's done, and the window would go with it.
+        None | Some("") => match crate::app_handle().filter(|_| !ONE_OFF.load(Ordering::Relaxed)) {
+            Some(app) => {
+                tray::show(app);
+                Reply::default()
+            }
+            None => Reply {
+                code: 1,
+                output: vec!["Luauncher is busy running something without its window, try again once it's done".to_owned()],
+            },
+        },
+        Some("--test") => {
+            let (output, code) = harness::run_tests_lines(args.get(1).map(String::as_str)).await;
+            Reply { code, output }
+        }
+        Some(_) => {
+            let game = args.join(" ");
+            let session = lua_utils::launch_game(&game).await;
+
+            Reply {
+                code: session.exit_code.unwrap_or(1),
+                output: session.error.map(|error| format!("{game} {}: {error}", session.outcome)).into_iter().collect(),
+            }
+        }
+    }
+}
+
+async fn send(write: &mut OwnedWriteHalf, line: &str) -> io::Result<()> {
+    write.write_all(format!("{line}\n").as_bytes()).await
+}
+
+async fn handle(stream: TcpStream, token: Arc<str>) -> io::Result<()> {
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+
+    let Some(line) = lines.next_line().await? else {
+        return Ok(());
+    };
+
+    let request: Request = serde_json::from_str(&line).map_err(io::Error::other)?;
+
+    // Anything without the token just gets hung up on.
+    if request.token != *token {
+        return Ok(());
+    }
+
+    send(&mut write, ACCEPTED).await?;
+
+    output::add_log(format!("[Single Instance] Another launch sent: {}", request.args.join(" ")), output::LogLevel::Info, true);
+
+    let reply = match Admitted::new() {
+        Some(_admitted) => execute(&request.args).await,
+        None => Reply {
+            code: 1,
+            output: vec!["Luauncher is closing, try again in a moment".to_owned()],
+        },
+    };
+    send(&mut write, &serde_json::to_string(&reply).map_err(io::Error::other)?).await
+}
+
+/// Lets later launches hand their arguments to this one, by listening on a local port and
+/// writing it to the instance file. Only for the launcher that `claim`ed the lock.
+pub(crate) async fn listen() {
+    let listener = match TcpListener::bind(("127.0.0.1", 0)).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            output::add_log(format!("[Single Instance] Failed to listen for other launches: {e}"), output::LogLevel::Warning, false);
+            return;
+        }
+    };
+
+    let token: Arc<str> = Uuid::new_v4().to_string().into();
+
+    let written = match (listener.local_addr(), instance_path()) {
+        (Ok(address), Some(path)) => path.parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, format!("{}\n{token}\n", address.port()))),
+        (Err(e), _) => Err(e),
+        (_, None) => Err(io::Error::new(io::ErrorKind::NotFound, "no config folder")),
+    };
+
+    if let Err(e) = written {
+        output::add_log(format!("[Single Instance] Failed to write the instance file: {e}"), output::LogLevel::Warning, false);
+        return;
+    }
+
+    tokio::spawn(async move {
+        loop {
+            let stream = match listener.accept().await {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    output::add_log(format!("[Single Instance] Failed to accept another launch: {e}"), output::LogLevel::Warning, true);
+                    continue;
+                }
+            };
+
+            let token = token.clone();
+
+            tokio::spawn(async move {
+                if let Err(e) = handle(stream, token).await {
+                    output::add_log(format!("[Single Instance] Failed to handle another launch: {e}"), output::LogLevel::Warning, true);
+                }
+            });
+        }
+    });
+}
+
+/// Connects to the launcher holding the lock. It may have only just taken it, so its port is
+/// looked for again until it turns up.
+async fn connect() -> Option<(TcpStream, String)> {
+    let deadline = Instant::now() + ANSWER_TIMEOUT;
+
+    loop {
+        if let Some((port, token)) = read_instance() {
+            if let Ok(Ok(stream)) = timeout(ANSWER_TIMEOUT, TcpStream::connect(("127.0.0.1", port))).await {
+                return Some((stream, token));
+            }
+        }
+
+        if Instant::now() >= deadline {
+            return None;
+        }
+
+        sleep(RETRY_INTERVAL).await;
+    }
+}
+
+/// Hands `args` to the launcher that holds the lock and returns what it did, or `None` when it
+/// doesn't answer.
+pub(crate) async fn forward(args: &[String]) -> Option<Reply> {
+    let (stream, token) = connect().await?;
+    let (read, mut write) = stream.into_split();
+    let mut lines = BufReader::new(read).lines();
+
+    let request = serde_json::to_string(&Request { token, args: args.to_vec() }).ok()?;
+    send(&mut write, &request).await.ok()?;
+
+    let answer = timeout(ANSWER_TIMEOUT, lines.next_line()).await.ok()?.ok()??;
+
+    if answer != ACCEPTED {
+        return None;
+    }
+
+    // The running launcher has it now, so losing it from here on is a failure, not a reason to start another.
+    let reply = match lines.next_line().await {
+        Ok(Some(line)) => serde_json::from_str(&line).unwrap_or_else(|e| Reply {
+            code: 1,
+            output: vec![format!("Luauncher sent back something unreadable: {e}")],
+        }),
+        _ => Reply {
+            code: 1,
+            output: vec!["Luauncher closed before it finished".to_owned()],
+        },
+    };
+
+    Some(reply)
+}
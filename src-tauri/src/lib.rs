@@ -21,6 +21,40 @@ use std::{fs::File, io::BufReader, path::Path};
 mod lua_utils;
 mod files;
 mod output;
+mod sources;
+mod templates;
+mod lint;
+mod simulate;
+mod os_layer;
+mod harness;
+mod window;
+mod process;
+mod matching;
+mod hooks;
+mod saves;
+mod fs_api;
+mod storage;
+mod http;
+mod formats;
+mod ini;
+mod sys;
+mod command;
+mod dialogs;
+mod notify;
+mod runs;
+mod modules;
+mod tasks;
+mod scheduler;
+mod tray;
+mod instance;
+
+static APP: once_cell::sync::OnceCell<tauri::AppHandle> = once_cell::sync::OnceCell::new();
+
+/// The running app, for code that isn't called through a command, like the script bindings.
+/// `None` when the launcher runs without its window, like with `--test`.
+pub(crate) fn app_handle() -> Option<&'static tauri::AppHandle> {
+    APP.get()
+}
 
 #[tauri::command]
 fn get_games() -> Vec<String> {
@@ -38,15 +72,30 @@ async fn run_game(gameName: String) -> Result<String, String> {
     Ok("Game executed Succsessfully.".to_string())
 }
 
+#[tauri::command]
+async fn simulate_game(gameName: String, scenario: Option<simulate::Scenario>) -> Result<Vec<simulate::TraceEntry>, String> {
+    lua_utils::lua_simulate_game(&gameName, scenario.unwrap_or_default())
+        .await
+        .map_err(|e| format!("Lua simulation error: {}", e))
+}
+
+#[tauri::command]
+async fn run_script_tests(filter: Option<String>) -> Result<Vec<harness::TestReport>, String> {
+    harness::run_tests(filter.as_deref()).await
+}
+
 #[tauri::command]
 fn save_settings(dark: bool, dev: bool, close: bool, games: HashMap<String, String>, iconupdates: bool) -> String {
-    let _ = files::save_settings(&files::Settings{
-        dark,
-        dev,
-        close,
-        games,
-        iconupdates
-    });
+    // The frontend only knows about these fields, the rest are kept as they are.
+    let mut settings = files::load_settings().ok().flatten().unwrap_or_default();
+
+    settings.dark = dark;
+    settings.dev = dev;
+    settings.close = close;
+    settings.games = games;
+    settings.iconupdates = iconupdates;
+
+    let _ = files::save_settings(&settings);
 
     "Saved Settings".to_string()
 }
@@ -76,6 +125,9 @@ fn restart_app() -> Result<(), String> {
 
     let current_exe = std::env::current_exe().map_err(|e| e.to_string())?;
 
+    // Otherwise the new launcher would find this one still running and hand itself over to it.
+    instance::release();
+
     Command::new(current_exe)
         .spawn()
         .map_err(|e| e.to_string())?;
@@ -83,8 +135,40 @@ fn restart_app() -> Result<(), String> {
     std::process::exit(0);
 }
 
+fn validate_script(name: &str, code: &str) -> Result<(), String> {
+    let diagnostics = lint::check_script(code);
+
+    for diagnostic in &diagnostics {
+        let level = match diagnostic.severity {
+            lint::Severity::Error => output::LogLevel::Error,
+            lint::Severity::Warning => output::LogLevel::Warning,
+        };
+
+        output::add_log(format!("[Checking Script] {}:{}:{}: {}", name, diagnostic.line, diagnostic.column, diagnostic.message), level, false);
+    }
+
+    if lint::has_errors(&diagnostics) {
+        let errors: Vec<String> = diagnostics.iter()
+            .filter(|d| d.severity == lint::Severity::Error)
+            .map(|d| format!("line {}, column {}: {}", d.line, d.column, d.message))
+            .collect();
+
+        return Err(format!("Script '{}' has errors: {}", name, errors.join("; ")));
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+fn check_script(code: String) -> Vec<lint::Diagnostic> {
+    lint::check_script(&code)
+}
+
 #[tauri::command]
 fn make_plugin(name: String, path: String, code: String) -> Result<String, String> {
+    let content = files::get_file_content(code);
+    validate_script(&name, &content)?;
+
     let mut settings = files::load_settings().unwrap().unwrap();
     let mut games = settings.games;
     games.insert(name.clone(), path);
@@ -93,8 +177,8 @@ fn make_plugin(name: String, path: String, code: String) -> Result<String, Strin
 
     let _ = files::save_settings(&settings);
 
-    let _ = files::save_script(&name, &files::get_file_content(code));
-    
+    let _ = files::save_script(&name, &content);
+
     Ok("Made Plugin".to_string())
 }
 
@@ -116,6 +200,12 @@ fn save_game(mut path: String, name: String, oldn: String) -> Result<String, Str
 
     settings.games = games;
 
+    for game in settings.favourites.iter_mut().chain(settings.recent.iter_mut()) {
+        if *game == oldn {
+            *game = name.clone();
+        }
+    }
+
     let oldnlua = format!("{}.lua", oldn);
 
     let scripts_dir = files::get_scripts_dir().unwrap();
@@ -125,6 +215,7 @@ fn save_game(mut path: String, name: String, oldn: String) -> Result<String, Str
     let _ = files::save_script(&name, &content);
 
     let _ = files::save_settings(&settings);
+    tray::refresh();
 
     #[cfg(target_os = "windows")]
     {
@@ -146,6 +237,13 @@ fn save_game(mut path: String, name: String, oldn: String) -> Result<String, Str
         let _ = files::create_icon(&path, &name);
     }
 
+    #[cfg(target_os = "linux")]
+    {
+        let icons_dir = files::get_icon_dir().unwrap();
+
+        let _ = std::fs::rename(icons_dir.join(format!("{}.png", oldn)), icons_dir.join(format!("{}.png", name)));
+    }
+
     Ok("Saved Game".to_string())
 }
 
@@ -160,8 +258,11 @@ fn delete_game(name: String) -> Result<String, String> {
     games.remove(&name);
 
     settings.games = games;
+    settings.favourites.retain(|game| *game != name);
+    settings.recent.retain(|game| *game != name);
 
     let _ = files::save_settings(&settings);
+    tray::refresh();
 
     #[cfg(target_os = "windows")]
     let _ = files::delete_file(files::get_icon_dir().unwrap().join(&format!("{}.ico", name)));
@@ -169,6 +270,9 @@ fn delete_game(name: String) -> Result<String, String> {
     #[cfg(target_os = "macos")]
     let _ = files::delete_file(files::get_icon_dir().unwrap().join(&format!("{}.icns", name)));
 
+    #[cfg(target_os = "linux")]
+    let _ = files::delete_file(files::get_icon_dir().unwrap().join(&format!("{}.png", name)));
+
     Ok("Deleted Game".to_string())
 }
 
@@ -176,6 +280,7 @@ fn delete_game(name: String) -> Result<String, String> {
 fn hide_app(app: tauri::AppHandle) -> Result<(), String> {
     if let Some(window) = app.get_webview_window("Luauncher") {
         window.hide().map_err(|e| e.to_string())?;
+        tray::refresh();
         Ok(())
     } else {
         output::add_log("[Hiding App] Failed to hide window.".to_owned(), output::LogLevel::Warning, false);
@@ -235,15 +340,15 @@ struct Release {
     name: String
 }
 
-#[tauri::command]
-async fn update() -> Result<String, String> {
+/// The newest release's version, or `None` without internet.
+async fn latest_version() -> Result<Option<String>, String> {
     let connected = TcpStream::connect_timeout(
         &("1.1.1.1:80".parse().unwrap()),
         Duration::from_secs(2)
     ).is_ok();
 
     if connected == false {
-        return Ok("No Internet".to_string());
+        return Ok(None);
     }
 
     let url = "https://api.github.com/repos/GlowyGhost/Luauncher/releases/latest";
@@ -261,13 +366,22 @@ async fn update() -> Result<String, String> {
 
     res.name.remove(0);
 
-    if res.name == env!("CARGO_PKG_VERSION") {
+    Ok(Some(res.name))
+}
+
+#[tauri::command]
+async fn update() -> Result<String, String> {
+    let Some(latest) = latest_version().await? else {
+        return Ok("No Internet".to_string());
+    };
+
+    if latest == env!("CARGO_PKG_VERSION") {
         return Ok("No Update".to_string());
     }
 
     let res_msg = MessageDialog::new()
         .set_title("Update")
-        .set_description(format!("Are you sure you want to update Luauncher from {} to {}?", env!("CARGO_PKG_VERSION"), res.name))
+        .set_description(format!("Are you sure you want to update Luauncher from {} to {}?", env!("CARGO_PKG_VERSION"), latest))
         .set_buttons(rfd::MessageButtons::YesNo)
         .show();
 
@@ -277,6 +391,17 @@ async fn update() -> Result<String, String> {
     Ok("Undid".to_string())
 }
 
+/// Lets the user know about a new version without asking to install it, that's left to the settings.
+async fn check_for_update() {
+    match latest_version().await {
+        Ok(Some(latest)) if latest != env!("CARGO_PKG_VERSION") => {
+            notify::launcher("Update available", format!("Luauncher {latest} is out, you have {}. You can update from the settings.", env!("CARGO_PKG_VERSION")));
+        }
+        Ok(_) => {}
+        Err(e) => output::add_log(format!("[Checking for Updates] {e}"), output::LogLevel::Warning, true),
+    }
+}
+
 #[tauri::command]
 fn get_version() -> String {
     env!("CARGO_PKG_VERSION").to_string()
@@ -414,13 +539,251 @@ fn get_icon(exePath: String, name: String) -> Result<Option<String>, String> {
     }
 
     #[cfg(not(any(target_os = "windows", target_os = "macos")))]
-    Ok(None)
+    {
+        let _ = exePath;
+
+        let icon_path = match files::get_icon_dir() {
+            Some(dir) => dir.join(format!("{}.png", name)),
+            None => return Ok(None),
+        };
+
+        if !icon_path.exists() {
+            return Ok(None);
+        }
+
+        let icon_bytes = std::fs::read(&icon_path).map_err(|e| format!("Failed to read icon file: {e}"))?;
+
+        Ok(Some(general_purpose::STANDARD.encode(&icon_bytes)))
+    }
+}
+
+#[tauri::command]
+fn scan_library_sources() -> Vec<sources::CandidateGame> {
+    sources::scan_all()
+}
+
+#[tauri::command]
+fn import_games(games: Vec<sources::CandidateGame>) -> Result<String, String> {
+    let count = sources::import(&games)?;
+
+    Ok(format!("Imported {count} games"))
+}
+
+#[tauri::command]
+fn get_templates() -> Vec<templates::Template> {
+    templates::list_templates()
+}
+
+#[tauri::command]
+fn render_template(id: String, values: HashMap<String, String>) -> Result<String, String> {
+    let template = templates::find_template(&id)
+        .ok_or_else(|| format!("Template '{}' not found", id))?;
+
+    templates::render(&template, &values)
+}
+
+#[tauri::command]
+fn save_template(template: templates::Template) -> Result<String, String> {
+    templates::save_template(template)?;
+
+    Ok("Saved Template".to_string())
+}
+
+#[tauri::command]
+fn delete_template(id: String) -> Result<String, String> {
+    templates::delete_template(&id)?;
+
+    Ok("Deleted Template".to_string())
+}
+
+#[tauri::command]
+fn make_plugin_from_template(name: String, path: String, id: String, values: HashMap<String, String>) -> Result<String, String> {
+    let code = render_template(id, values)?;
+    validate_script(&name, &code)?;
+
+    let mut settings = files::load_settings()
+        .map_err(|e| e.to_string())?
+        .ok_or("Settings not found")?;
+
+    // The script goes first, so a game is never added without one.
+    files::save_script(&name, &code).map_err(|e| e.to_string())?;
+
+    settings.games.insert(name.clone(), path);
+    files::save_settings(&settings).map_err(|e| e.to_string())?;
+
+    Ok("Made Plugin".to_string())
+}
+
+/// Save snapshots are kept in a folder named after the game, so it has to be a plain name.
+fn check_game_name(game: &str) -> Result<(), String> {
+    if files::is_plain_name(game) {
+        Ok(())
+    } else {
+        Err(format!("'{game}' isn't a valid game name"))
+    }
+}
+
+#[tauri::command]
+fn get_save_config(gameName: String) -> Result<Option<saves::SaveConfig>, String> {
+    check_game_name(&gameName)?;
+    saves::get_config(&gameName).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn set_save_config(gameName: String, config: Option<saves::SaveConfig>) -> Result<String, String> {
+    check_game_name(&gameName)?;
+    saves::set_config(&gameName, config).map_err(|e| e.to_string())?;
+
+    Ok("Saved Save Folder".to_string())
+}
+
+#[tauri::command]
+fn list_save_snapshots(gameName: String) -> Result<Vec<saves::Snapshot>, String> {
+    check_game_name(&gameName)?;
+    saves::list(&gameName).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn backup_saves(gameName: String) -> Result<saves::Snapshot, String> {
+    check_game_name(&gameName)?;
+    saves::backup(&gameName, "manual").map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+fn restore_saves(gameName: String, id: String) -> Result<String, String> {
+    check_game_name(&gameName)?;
+    saves::restore(&gameName, &id).map_err(|e| e.to_string())?;
+
+    Ok("Restored Saves".to_string())
+}
+
+#[tauri::command]
+fn get_fs_roots() -> Vec<String> {
+    files::load_settings().ok().flatten().map(|s| s.fs_roots).unwrap_or_default()
+}
+
+#[tauri::command]
+fn set_fs_roots(roots: Vec<String>) -> Result<String, String> {
+    if let Some(root) = roots.iter().find(|root| !files::expand_home(root).is_absolute()) {
+        return Err(format!("'{root}' isn't a full path"));
+    }
+
+    let mut settings = files::load_settings()
+        .map_err(|e| e.to_string())?
+        .ok_or("Settings not found")?;
+
+    settings.fs_roots = roots;
+    files::save_settings(&settings).map_err(|e| e.to_string())?;
+
+    Ok("Saved Folders".to_string())
+}
+
+#[tauri::command]
+fn get_capabilities() -> HashMap<String, Vec<String>> {
+    files::load_settings().ok().flatten().map(|s| s.capabilities).unwrap_or_default()
+}
+
+#[tauri::command]
+fn set_capabilities(capabilities: HashMap<String, Vec<String>>) -> Result<String, String> {
+    let mut settings = files::load_settings()
+        .map_err(|e| e.to_string())?
+        .ok_or("Settings not found")?;
+
+    settings.capabilities = capabilities;
+    files::save_settings(&settings).map_err(|e| e.to_string())?;
+
+    Ok("Saved Capabilities".to_string())
+}
+
+#[tauri::command]
+fn get_rules() -> Vec<scheduler::Rule> {
+    files::load_settings().ok().flatten().map(|s| s.rules).unwrap_or_default()
+}
+
+#[tauri::command]
+fn set_rules(rules: Vec<scheduler::Rule>) -> Result<String, String> {
+    for rule in &rules {
+        rule.validate()?;
+    }
+
+    let mut settings = files::load_settings()
+        .map_err(|e| e.to_string())?
+        .ok_or("Settings not found")?;
+
+    settings.rules = rules;
+    files::save_settings(&settings).map_err(|e| e.to_string())?;
+
+    Ok("Saved Rules".to_string())
+}
+
+#[tauri::command]
+fn set_favourite(gameName: String, favourite: bool) -> Result<String, String> {
+    let mut settings = files::load_settings()
+        .map_err(|e| e.to_string())?
+        .ok_or("Settings not found")?;
+
+    settings.favourites.retain(|game| *game != gameName);
+
+    if favourite {
+        settings.favourites.push(gameName);
+    }
+
+    files::save_settings(&settings).map_err(|e| e.to_string())?;
+    tray::refresh();
+
+    Ok("Saved Favourites".to_string())
+}
+
+#[tauri::command]
+fn cancel_run(gameName: String) -> bool {
+    runs::cancel(&gameName)
+}
+
+#[tauri::command]
+fn get_dialogs() -> Vec<dialogs::PendingDialog> {
+    dialogs::pending()
+}
+
+#[tauri::command]
+fn answer_dialog(id: u64, answer: serde_json::Value) -> Result<(), String> {
+    dialogs::answer(id, answer)
+}
+
+#[tauri::command]
+fn get_runs() -> Vec<runs::Run> {
+    runs::list()
 }
 
 #[tokio::main]
 async fn main() {
+    let cli_args: Vec<String> = std::env::args().collect();
+
+    // Another Luauncher is running, so it does the work instead of two of them using the same files.
+    if !instance::claim() {
+        let reply = instance::forward(cli_args.get(1..).unwrap_or_default()).await.unwrap_or_else(|| instance::Reply {
+            code: 1,
+            output: vec!["Luauncher is already running but didn't answer".to_owned()],
+        });
+
+        reply.print();
+        std::process::exit(reply.code);
+    }
+
+    instance::listen().await;
+
+    if cli_args.get(1).map(String::as_str) == Some("--test") {
+        instance::set_one_off();
+        let code = harness::run_tests_cli(cli_args.get(2).map(String::as_str)).await;
+        instance::finish().await;
+        instance::release();
+        std::process::exit(code);
+    }
+
     tauri::Builder::default()
+        .plugin(tauri_plugin_dialog::init())
         .setup(|app| {
+            let _ = APP.set(app.handle().clone());
+
             if let Some(window) = app.get_webview_window("Luauncher") {
                 window.hide().unwrap();
 
@@ -428,19 +791,27 @@ async fn main() {
 
                 if args.len() > 1 && !args[1].is_empty() {
                     args.remove(0);
-
-                    let main_arg = args.join(" ");
-
-                    tokio::task::block_in_place(move || {
-                        let rt = tokio::runtime::Handle::current();
-                        rt.block_on(async move {
-                            let _ = run_game(main_arg).await;
-                            std::process::exit(0);
-                        });
+                    instance::set_one_off();
+
+                    // Run beside the event loop rather than blocking setup, so the frontend loads
+                    // in the hidden window and can show the script's dialogs.
+                    tokio::spawn(async move {
+                        let reply = instance::execute(&args).await;
+                        instance::finish().await;
+                        reply.print();
+                        instance::release();
+                        std::process::exit(reply.code);
                     });
                 } else {
                     files::make_dirs();
                     window.show().unwrap();
+
+                    tokio::spawn(check_for_update());
+                    scheduler::start();
+
+                    if let Err(e) = tray::create(app.handle()) {
+                        output::add_log(format!("[App Initialization] Failed to add the tray icon: {e}"), output::LogLevel::Warning, false);
+                    }
                 }
             } else {
                 output::add_log("[App Initialization] No window labeled 'Luauncher' found.".to_owned(), output::LogLevel::Warning, false);
@@ -450,7 +821,16 @@ async fn main() {
             Ok(())})
         .invoke_handler(tauri::generate_handler![get_games, run_game, save_settings, get_settings, restart_app, hide_app,
             get_icon, get_game_path, make_plugin, save_game, delete_game, save_log, uninstall, update, get_version, open_link,
-            get_logs, create_shortcut])
-        .run(tauri::generate_context!())
-        .expect("error while running tauri application");
+            get_logs, create_shortcut, scan_library_sources, import_games,
+            get_templates, render_template, save_template, delete_template, make_plugin_from_template, check_script,
+            simulate_game, run_script_tests, get_save_config, set_save_config, list_save_snapshots, backup_saves, restore_saves,
+            get_fs_roots, set_fs_roots, get_capabilities, set_capabilities, get_dialogs, answer_dialog, get_runs, get_rules, set_rules,
+            set_favourite, cancel_run])
+        .build(tauri::generate_context!())
+        .expect("error while running tauri application")
+        .run(|_, event| {
+            if let tauri::RunEvent::Exit = event {
+                instance::release();
+            }
+        });
 }
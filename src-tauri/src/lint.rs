@@ -0,0 +1,653 @@
+use mlua::{Lua, Value};
+use serde::Serialize;
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+use crate::lua_utils::{get_custom_lua, system};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct Diagnostic {
+    pub(crate) severity: Severity,
+    pub(crate) line: usize,
+    pub(crate) column: usize,
+    pub(crate) message: String,
+}
+
+/// Minimum and maximum argument counts of the Luauncher bindings.
+const ARITY: &[(&str, usize, usize)] = &[
+    ("openApp", 1, 2),
+    ("closeApp", 1, 1),
+    ("forceCloseApp", 1, 1),
+    ("isAppOpen", 1, 2),
+    ("isWindowOpen", 1, 2),
+    ("openURL", 1, 1),
+    ("exit", 0, 1),
+    ("runCommand", 1, 2),
+    ("waitUntilWindowClose", 1, 2),
+    ("waitUntilAppClose", 1, 2),
+    ("waitUntilWindowOpen", 2, 3),
+    ("waitUntilAppOpen", 2, 3),
+    ("sleep", 1, 1),
+    ("messageBox", 2, 2),
+    ("log", 3, 3),
+    ("requireApi", 1, 1),
+    ("onSystem", 1, 1),
+    ("retry", 1, 2),
+    ("withTimeout", 2, 3),
+    ("waitAny", 2, 2),
+    ("waitAll", 2, 2),
+    ("launchAndWait", 1, 2),
+    ("confirm", 2, 2),
+    ("choose", 2, 2),
+    ("prompt", 1, 2),
+    ("pickFile", 0, 1),
+    ("pickFolder", 0, 1),
+    ("notify", 1, 3),
+    ("progress", 1, 2),
+    ("status", 0, 1),
+];
+
+/// Bindings whose first argument is an app or executable name.
+const APP_BINDINGS: &[&str] = &["openApp", "closeApp", "forceCloseApp", "isAppOpen", "waitUntilAppOpen", "waitUntilAppClose"];
+
+#[derive(Clone, Debug, PartialEq)]
+enum Kind {
+    Name,
+    Keyword,
+    Str(String),
+    Number,
+    Symbol,
+}
+
+#[derive(Clone, Debug)]
+struct Token {
+    kind: Kind,
+    text: String,
+    line: usize,
+    column: usize,
+}
+
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in",
+    "local", "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// Compiles the script without running it and lints it against the Luauncher bindings.
+pub(crate) fn check_script(code: &str) -> Vec<Diagnostic> {
+    if let Some(diagnostic) = syntax_error(code) {
+        return vec![diagnostic];
+    }
+
+    let Some(tokens) = tokenize(code) else { return Vec::new() };
+
+    lint(&tokens, known_globals())
+}
+
+pub(crate) fn has_errors(diagnostics: &[Diagnostic]) -> bool {
+    diagnostics.iter().any(|d| d.severity == Severity::Error)
+}
+
+fn syntax_error(code: &str) -> Option<Diagnostic> {
+    let lua = Lua::new();
+    let err = lua.load(code).set_name("=script").into_function().err()?;
+
+    let message = match &err {
+        mlua::Error::SyntaxError { message, .. } => message.clone(),
+        other => other.to_string(),
+    };
+
+    let (line, rest) = message.strip_prefix("script:")
+        .and_then(|rest| rest.split_once(':'))
+        .and_then(|(line, rest)| Some((line.parse::<usize>().ok()?, rest.trim().to_owned())))
+        .unwrap_or((1, message.clone()));
+
+    let source_line = code.lines().nth(line.saturating_sub(1)).unwrap_or("");
+    let column = match rest.rsplit_once("near '") {
+        Some((_, near)) => {
+            let near = near.trim_end_matches('\'');
+            source_line.find(near).map(|i| source_line[..i].chars().count() + 1).unwrap_or(1)
+        }
+        None if rest.ends_with("near <eof>") => source_line.chars().count() + 1,
+        None => 1,
+    };
+
+    Some(Diagnostic {
+        severity: Severity::Error,
+        line,
+        column,
+        message: rest,
+    })
+}
+
+/// Every global a script starts with. They're the same for every script, so the Lua state
+/// is only built for the first check.
+fn known_globals() -> &'static HashSet<String> {
+    static GLOBALS: OnceLock<HashSet<String>> = OnceLock::new();
+
+    GLOBALS.get_or_init(|| {
+        let lua = get_custom_lua();
+        let mut names = HashSet::new();
+
+        for (key, _) in lua.globals().pairs::<Value, Value>().flatten() {
+            if let Value::String(name) = key {
+                names.insert(name.to_string_lossy().to_string());
+            }
+        }
+
+        names
+    })
+}
+
+fn long_bracket_level(chars: &[char], i: usize) -> Option<usize> {
+    if chars.get(i) != Some(&'[') {
+        return None;
+    }
+
+    let mut level = 0;
+    while chars.get(i + 1 + level) == Some(&'=') {
+        level += 1;
+    }
+
+    (chars.get(i + 1 + level) == Some(&'[')).then_some(level)
+}
+
+/// Returns the index just past the closing bracket of a long string starting at `i`.
+fn skip_long_bracket(chars: &[char], i: usize, level: usize, line: &mut usize) -> Option<usize> {
+    let mut j = i + level + 2;
+
+    while j < chars.len() {
+        if chars[j] == '\n' {
+            *line += 1;
+        }
+
+        if chars[j] == ']' && (1..=level).all(|k| chars.get(j + k) == Some(&'=')) && chars.get(j + level + 1) == Some(&']') {
+            return Some(j + level + 2);
+        }
+
+        j += 1;
+    }
+
+    None
+}
+
+fn tokenize(code: &str) -> Option<Vec<Token>> {
+    let chars: Vec<char> = code.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    let mut line = 1;
+    let mut line_start = 0;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i - line_start + 1;
+
+        if c == '\n' {
+            line += 1;
+            i += 1;
+            line_start = i;
+            continue;
+        }
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c == '-' && chars.get(i + 1) == Some(&'-') {
+            if let Some(level) = long_bracket_level(&chars, i + 2) {
+                let before = line;
+                i = skip_long_bracket(&chars, i + 2, level, &mut line)?;
+                if line != before {
+                    line_start = chars[..i].iter().rposition(|&c| c == '\n').map(|p| p + 1).unwrap_or(0);
+                }
+            } else {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            continue;
+        }
+
+        if let Some(level) = long_bracket_level(&chars, i) {
+            let start_line = line;
+            let end = skip_long_bracket(&chars, i, level, &mut line)?;
+            let text: String = chars[i + level + 2..end - level - 2].iter().collect();
+
+            tokens.push(Token { kind: Kind::Str(text.clone()), text, line: start_line, column });
+
+            if line != start_line {
+                line_start = chars[..end].iter().rposition(|&c| c == '\n').map(|p| p + 1).unwrap_or(0);
+            }
+            i = end;
+            continue;
+        }
+
+        if c == '"' || c == '\'' {
+            let mut value = String::new();
+            let mut j = i + 1;
+
+            while j < chars.len() && chars[j] != c {
+                if chars[j] == '\\' {
+                    j += 1;
+                    match chars.get(j) {
+                        Some('n') => value.push('\n'),
+                        Some('t') => value.push('\t'),
+                        Some(&other) => value.push(other),
+                        None => {}
+                    }
+                } else {
+                    value.push(chars[j]);
+                }
+                j += 1;
+            }
+
+            tokens.push(Token { kind: Kind::Str(value), text: chars[i..(j + 1).min(chars.len())].iter().collect(), line, column });
+            i = j + 1;
+            continue;
+        }
+
+        if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(|n| n.is_ascii_digit())) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '.'
+                || ((chars[i] == '-' || chars[i] == '+') && matches!(chars[i - 1], 'e' | 'E' | 'p' | 'P'))) {
+                i += 1;
+            }
+
+            tokens.push(Token { kind: Kind::Number, text: chars[start..i].iter().collect(), line, column });
+            continue;
+        }
+
+        if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            let text: String = chars[start..i].iter().collect();
+            let kind = if KEYWORDS.contains(&text.as_str()) { Kind::Keyword } else { Kind::Name };
+
+            tokens.push(Token { kind, text, line, column });
+            continue;
+        }
+
+        let three: String = chars[i..(i + 3).min(chars.len())].iter().collect();
+        let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+
+        let symbol = if three == "..." {
+            three
+        } else if ["==", "~=", "<=", ">=", "..", "::", "//", "<<", ">>"].contains(&two.as_str()) {
+            two
+        } else {
+            c.to_string()
+        };
+
+        i += symbol.chars().count();
+        tokens.push(Token { kind: Kind::Symbol, text: symbol, line, column });
+    }
+
+    Some(tokens)
+}
+
+fn is_symbol(token: Option<&Token>, symbol: &str) -> bool {
+    token.is_some_and(|t| t.kind == Kind::Symbol && t.text == symbol)
+}
+
+fn is_keyword(token: Option<&Token>, keyword: &str) -> bool {
+    token.is_some_and(|t| t.kind == Kind::Keyword && t.text == keyword)
+}
+
+/// Names the script defines itself: locals, functions, parameters, loop variables and global assignments.
+fn defined_names(tokens: &[Token]) -> HashSet<String> {
+    let mut names = HashSet::new();
+    let mut i = 0;
+
+    while i < tokens.len() {
+        let token = &tokens[i];
+
+        match (&token.kind, token.text.as_str()) {
+            (Kind::Keyword, "local") | (Kind::Keyword, "for") => {
+                let mut j = i + 1;
+                if is_keyword(tokens.get(j), "function") {
+                    j += 1;
+                }
+
+                while let Some(t) = tokens.get(j) {
+                    if t.kind == Kind::Name {
+                        names.insert(t.text.clone());
+                    } else if !(is_symbol(Some(t), ",") || is_symbol(Some(t), "<") || is_symbol(Some(t), ">")) {
+                        break;
+                    }
+                    j += 1;
+                }
+            }
+            (Kind::Keyword, "function") => {
+                if let Some(name) = tokens.get(i + 1).filter(|t| t.kind == Kind::Name) {
+                    names.insert(name.text.clone());
+                }
+
+                let mut j = i + 1;
+                while j < tokens.len() && !is_symbol(tokens.get(j), "(") {
+                    j += 1;
+                }
+                while let Some(t) = tokens.get(j + 1) {
+                    j += 1;
+                    if is_symbol(Some(t), ")") {
+                        break;
+                    }
+                    if t.kind == Kind::Name {
+                        names.insert(t.text.clone());
+                    }
+                }
+            }
+            (Kind::Name, _) => {
+                let previous = i.checked_sub(1).and_then(|p| tokens.get(p));
+                let is_field = is_symbol(previous, ".") || is_symbol(previous, ":");
+
+                if !is_field && is_symbol(tokens.get(i + 1), "=") {
+                    names.insert(token.text.clone());
+                }
+            }
+            _ => {}
+        }
+
+        i += 1;
+    }
+
+    names
+}
+
+/// Counts the arguments of the call whose `(` is at `open`. Returns `None` when the count
+/// isn't fixed, i.e. the last argument is `...` or another call that can return several values.
+fn count_args(tokens: &[Token], open: usize) -> Option<usize> {
+    let mut depth = 0;
+    let mut count = 0;
+    let mut arg_start = open + 1;
+    let mut last_arg = (open + 1, open + 1);
+
+    for (j, token) in tokens.iter().enumerate().skip(open) {
+        if token.kind != Kind::Symbol {
+            continue;
+        }
+
+        match token.text.as_str() {
+            "(" | "{" | "[" => depth += 1,
+            ")" | "}" | "]" => {
+                depth -= 1;
+                if depth == 0 {
+                    if j > arg_start {
+                        count += 1;
+                        last_arg = (arg_start, j);
+                    }
+                    break;
+                }
+            }
+            "," if depth == 1 => {
+                count += 1;
+                arg_start = j + 1;
+            }
+            _ => {}
+        }
+    }
+
+    let (start, end) = last_arg;
+    if end > start {
+        let last = &tokens[start..end];
+        let open_ended = is_symbol(last.last(), "...")
+            || (is_symbol(last.last(), ")") && last.first().is_some_and(|t| t.kind == Kind::Name));
+
+        if open_ended {
+            return None;
+        }
+    }
+
+    Some(count)
+}
+
+fn os_of_condition(tokens: &[Token]) -> Option<String> {
+    let mentions_system = tokens.iter().any(|t| t.kind == Kind::Name && t.text == "system");
+    let is_negated = tokens.iter().any(|t| t.text == "~=" || t.text == "not");
+
+    if !mentions_system || is_negated {
+        return None;
+    }
+
+    tokens.iter().find_map(|t| match &t.kind {
+        Kind::Str(value) if ["Windows", "Linux", "MacOS"].contains(&value.as_str()) => Some(value.clone()),
+        _ => None,
+    })
+}
+
+fn os_warning(binding: &str, arg: &str, current_os: &str) -> Option<String> {
+    let lower = arg.to_lowercase();
+
+    if !APP_BINDINGS.contains(&binding) {
+        return None;
+    }
+
+    if lower.ends_with(".exe") && current_os != "Windows" {
+        Some(format!("'{arg}' is a Windows executable and won't be found on {current_os}"))
+    } else if lower.ends_with(".app") && current_os != "MacOS" {
+        Some(format!("'{arg}' is a macOS app bundle and won't be found on {current_os}"))
+    } else {
+        None
+    }
+}
+
+fn lint(tokens: &[Token], globals: &HashSet<String>) -> Vec<Diagnostic> {
+    let defined = defined_names(tokens);
+    let arity = |name: &str| ARITY.iter().find(|(n, _, _)| *n == name).map(|&(_, min, max)| (min, max));
+    let current_os = system();
+
+    let mut diagnostics = Vec::new();
+    let mut reported = HashSet::new();
+    let mut blocks: Vec<Option<String>> = Vec::new();
+
+    for (i, token) in tokens.iter().enumerate() {
+        if token.kind == Kind::Keyword {
+            match token.text.as_str() {
+                "if" | "elseif" => {
+                    let then = tokens[i..].iter().position(|t| is_keyword(Some(t), "then")).map(|p| p + i).unwrap_or(i);
+                    let os = os_of_condition(&tokens[i + 1..then]);
+
+                    if token.text == "if" {
+                        blocks.push(os);
+                    } else if let Some(top) = blocks.last_mut() {
+                        *top = os;
+                    }
+                }
+                "else" => {
+                    if let Some(top) = blocks.last_mut() {
+                        *top = None;
+                    }
+                }
+                "function" | "do" | "repeat" => blocks.push(None),
+                "end" | "until" => {
+                    blocks.pop();
+                }
+                _ => {}
+            }
+            continue;
+        }
+
+        if token.kind != Kind::Name {
+            continue;
+        }
+
+        let previous = i.checked_sub(1).and_then(|p| tokens.get(p));
+        if is_symbol(previous, ".") || is_symbol(previous, ":") || is_keyword(previous, "function") {
+            continue;
+        }
+
+        let next = tokens.get(i + 1);
+        let is_call = is_symbol(next, "(") || is_symbol(next, "{") || next.is_some_and(|t| matches!(t.kind, Kind::Str(_)));
+        let is_index = is_symbol(next, ".") || is_symbol(next, ":");
+
+        if !is_call && !is_index {
+            continue;
+        }
+
+        let name = token.text.as_str();
+
+        if !globals.contains(name) && !defined.contains(name) {
+            if reported.insert(name.to_owned()) {
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    line: token.line,
+                    column: token.column,
+                    message: format!("Unknown global '{name}'"),
+                });
+            }
+            continue;
+        }
+
+        if !is_call || defined.contains(name) {
+            continue;
+        }
+
+        let count = if is_symbol(next, "(") { count_args(tokens, i + 1) } else { Some(1) };
+
+        if let (Some(count), Some((min, max))) = (count, arity(name)) {
+            if count < min || count > max {
+                let expected = if min == max { min.to_string() } else { format!("{min} to {max}") };
+
+                diagnostics.push(Diagnostic {
+                    severity: Severity::Warning,
+                    line: token.line,
+                    column: token.column,
+                    message: format!("'{name}' takes {expected} argument(s) but was called with {count}"),
+                });
+            }
+        }
+
+        let guarded_os = blocks.iter().rev().find_map(|os| os.clone());
+        if guarded_os.as_deref().is_some_and(|os| os != current_os) {
+            continue;
+        }
+
+        let first_arg = if is_symbol(next, "(") { tokens.get(i + 2) } else { next };
+        let literal = match first_arg.map(|t| &t.kind) {
+            Some(Kind::Str(value)) => value.as_str(),
+            _ => "",
+        };
+
+        if let Some(message) = os_warning(name, literal, &current_os) {
+            diagnostics.push(Diagnostic {
+                severity: Severity::Warning,
+                line: token.line,
+                column: token.column,
+                message,
+            });
+        }
+    }
+
+    diagnostics
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(code: &str) -> Vec<(Kind, String)> {
+        tokenize(code).unwrap().into_iter().map(|t| (t.kind, t.text)).collect()
+    }
+
+    fn messages(code: &str) -> Vec<String> {
+        check_script(code).into_iter().map(|d| d.message).collect()
+    }
+
+    #[test]
+    fn tokenizer() {
+        let name = |s: &str| (Kind::Name, s.to_owned());
+        let symbol = |s: &str| (Kind::Symbol, s.to_owned());
+        let number = |s: &str| (Kind::Number, s.to_owned());
+
+        let cases: Vec<(&str, Vec<(Kind, String)>)> = vec![
+            ("local x = 1", vec![(Kind::Keyword, "local".to_owned()), name("x"), symbol("="), number("1")]),
+            ("a..b ~= c", vec![name("a"), symbol(".."), name("b"), symbol("~="), name("c")]),
+            ("f(...)", vec![name("f"), symbol("("), symbol("..."), symbol(")")]),
+            ("1e-5 0x1p+4 .5", vec![number("1e-5"), number("0x1p+4"), number(".5")]),
+            ("-- comment\nx --[[ long\ncomment ]] y", vec![name("x"), name("y")]),
+            ("'it\\'s' \"a\\nb\"", vec![(Kind::Str("it's".to_owned()), "'it\\'s'".to_owned()), (Kind::Str("a\nb".to_owned()), "\"a\\nb\"".to_owned())]),
+            ("[==[a]]b]==]", vec![(Kind::Str("a]]b".to_owned()), "a]]b".to_owned())]),
+        ];
+
+        for (code, expected) in cases {
+            assert_eq!(kinds(code), expected, "{code}");
+        }
+
+        let tokens = tokenize("a\n  --[[\n]] b\n[[\n]] c").unwrap();
+        let positions: Vec<(usize, usize)> = tokens.iter().map(|t| (t.line, t.column)).collect();
+        assert_eq!(positions, [(1, 1), (3, 4), (4, 1), (5, 4)]);
+    }
+
+    #[test]
+    fn argument_counts() {
+        let cases: &[(&str, Option<usize>)] = &[
+            ("f()", Some(0)),
+            ("f(a)", Some(1)),
+            ("f(a, b)", Some(2)),
+            ("f(g(a, b), c)", Some(2)),
+            ("f({1, 2}, t[1], \"x, y\")", Some(3)),
+            ("f(a, ...)", None),
+            ("f(a, g())", None),
+            ("f(a, (g()))", Some(2)),
+        ];
+
+        for (code, count) in cases {
+            let tokens = tokenize(code).unwrap();
+            assert_eq!(count_args(&tokens, 1), *count, "{code}");
+        }
+    }
+
+    #[test]
+    fn warnings() {
+        let cases: &[(&str, &[&str])] = &[
+            ("openApp(\"steam\")", &[]),
+            ("notAThing()", &["Unknown global 'notAThing'"]),
+            ("local function helper() end\nhelper()", &[]),
+            ("helper = function() end\nhelper(1, 2, 3)", &[]),
+            ("closeApp()", &["'closeApp' takes 1 argument(s) but was called with 0"]),
+            ("openApp(\"a\", {}, 3)", &["'openApp' takes 1 to 2 argument(s) but was called with 3"]),
+            ("openApp(...)", &[]),
+            ("confirm(\"Update?\")", &["'confirm' takes 2 argument(s) but was called with 1"]),
+            ("progress(0.5, \"Downloading\", 3)", &["'progress' takes 1 to 2 argument(s) but was called with 3"]),
+            ("sleep \"1000\"", &[]),
+        ];
+
+        for (code, expected) in cases {
+            assert_eq!(messages(code), *expected, "{code}");
+        }
+    }
+
+    #[test]
+    fn os_warnings_respect_guards() {
+        let current = system();
+        let other = if current == "Windows" { "Linux" } else { "Windows" };
+        let exe = messages("openApp(\"game.exe\")");
+        let app = messages("isAppOpen(\"Game.app\")");
+
+        assert_eq!(exe.is_empty(), current == "Windows", "{exe:?}");
+        assert_eq!(app.is_empty(), current == "MacOS", "{app:?}");
+
+        // Code only run on another OS isn't warned about, code only run on this one is.
+        let guarded = |os: &str| messages(&format!("if system == \"{os}\" then\n    openApp(\"game.exe\")\n    openApp(\"Game.app\")\nend"));
+        assert!(guarded(other).is_empty());
+        assert_eq!(guarded(&current).len(), usize::from(current != "Windows") + usize::from(current != "MacOS"));
+        assert_eq!(messages("if system ~= \"Windows\" then openApp(\"game.exe\") end").len(), usize::from(current != "Windows"));
+    }
+
+    #[test]
+    fn syntax_errors() {
+        let diagnostics = check_script("local x = \nopenApp(\"a\"\n");
+
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, Severity::Error);
+        assert_eq!(diagnostics[0].line, 3);
+    }
+}
@@ -1,20 +1,62 @@
-use mlua::{Lua, Value};
+use mlua::Lua;
 use mlua::prelude::*;
-use std::process::Command;
 use std::path::PathBuf;
-use tokio::time::{sleep, Duration};
-use rfd::MessageDialog;
-
-#[cfg(target_os = "windows")]
-use winapi::um::winuser::FindWindowA;
-#[cfg(target_os = "windows")]
-use std::{ffi::CString, ptr::null_mut};
+use std::sync::Arc;
+use std::time::Duration;
 
+use crate::command::CommandRequest;
+use crate::dialogs;
 use crate::files::{self, get_scripts_dir};
+use crate::fs_api;
+use crate::hooks;
+use crate::matching::{Found, Lookup, LookupKind};
+use crate::modules;
+use crate::notify::{self, Notification};
+use crate::os_layer::{os, DeferredExit, OsLayer, RealOs};
 use crate::output;
+use crate::runs;
+use crate::saves;
+use crate::simulate;
+use crate::storage;
+use crate::http;
+use crate::formats;
+use crate::ini;
+use crate::sys;
+use crate::tasks;
+use crate::window;
+
+/// The game a Lua state runs for, set from Rust when it's made. Scripts can change the `game`
+/// global, so anything that picks folders or settings by game goes by this instead.
+struct Game(String);
+
+/// Sets the game `lua` runs for, and the `game` global scripts see it as.
+pub(crate) fn set_game(lua: &Lua, game: &str) -> LuaResult<()> {
+    lua.set_app_data(Game(game.to_owned()));
+    lua.globals().set("game", game)
+}
+
+/// The game `lua` runs for, or `None` for code that isn't a game's. It's used as a file name, so
+/// anything but a plain name is an error.
+pub(crate) fn current_game(lua: &Lua) -> LuaResult<Option<String>> {
+    let Some(game) = lua.app_data_ref::<Game>().map(|game| game.0.clone()) else {
+        return Ok(None);
+    };
 
-fn get_custom_lua() -> Lua {
+    if !files::is_plain_name(&game) {
+        return Err(LuaError::external(format!("'{game}' isn't a valid game name")));
+    }
+
+    Ok(Some(game))
+}
+
+pub(crate) fn get_custom_lua() -> Lua {
+    get_custom_lua_with(Arc::new(RealOs))
+}
+
+pub(crate) fn get_custom_lua_with(os_layer: Arc<dyn OsLayer>) -> Lua {
     let lua = Lua::new();
+    lua.set_app_data(os_layer);
+
     let globals = lua.globals();
 
     let _ = globals.set("openApp", lua.create_function(open_app).unwrap());
@@ -24,36 +66,45 @@ fn get_custom_lua() -> Lua {
     let _ = globals.set("isWindowOpen", lua.create_function(is_window_open).unwrap());
     let _ = globals.set("openURL", lua.create_function(open_url).unwrap());
     let _ = globals.set("exit", lua.create_function(exit).unwrap());
-    let _ = globals.set("runCommand", lua.create_function(command).unwrap());
+    let _ = globals.set("runCommand", lua.create_async_function(|lua, (cmd, options): (LuaValue, Option<LuaValue>)| async move {
+        command(&lua, cmd, options).await
+    }).unwrap());
 
-    let _ = globals.set("waitUntilWindowClose", lua.create_async_function(|_lua, window_name: String| async move {
-        wait_until_window_closed_async(window_name).await
+    let _ = globals.set("waitUntilWindowClose", lua.create_async_function(|lua, (window_name, options): (String, Option<LuaValue>)| async move {
+        let lookup = Lookup::from_lua(&lua, LookupKind::Window, &window_name, options)?;
+        let found = wait_until_closed_async(os(&lua), &lookup, "waitUntilWindowClose").await?;
+        if lookup.wants_object { lua.to_value(&found) } else { Ok(LuaValue::Nil) }
     }).unwrap());
 
-    let _ = globals.set("waitUntilAppClose", lua.create_async_function(|_lua, process_name: String| async move {
-        wait_until_app_closed_async(process_name).await
+    let _ = globals.set("waitUntilAppClose", lua.create_async_function(|lua, (process_name, options): (String, Option<LuaValue>)| async move {
+        let lookup = Lookup::from_lua(&lua, LookupKind::App, &process_name, options)?;
+        let found = wait_until_closed_async(os(&lua), &lookup, "waitUntilAppClose").await?;
+        if lookup.wants_object { lua.to_value(&found) } else { Ok(LuaValue::Nil) }
     }).unwrap());
 
-    let _ = globals.set("waitUntilWindowOpen", lua.create_async_function(|_lua, (window_name, timeout): (String, u64)| async move {
-        wait_window_opened_async(window_name, timeout).await
+    let _ = globals.set("waitUntilWindowOpen", lua.create_async_function(|lua, (window_name, timeout, options): (String, u64, Option<LuaValue>)| async move {
+        let lookup = Lookup::from_lua(&lua, LookupKind::Window, &window_name, options)?;
+        let found = wait_opened_async(os(&lua), &lookup, timeout, "waitUntilWindowOpen").await?;
+        lookup.result(&lua, found)
     }).unwrap());
 
-    let _ = globals.set("waitUntilAppOpen", lua.create_async_function(|_lua, (process_name, timeout): (String, u64)| async move {
-        wait_app_opened_async(process_name, timeout).await
+    let _ = globals.set("waitUntilAppOpen", lua.create_async_function(|lua, (process_name, timeout, options): (String, u64, Option<LuaValue>)| async move {
+        let lookup = Lookup::from_lua(&lua, LookupKind::App, &process_name, options)?;
+        let found = wait_opened_async(os(&lua), &lookup, timeout, "waitUntilAppOpen").await?;
+        lookup.result(&lua, found)
     }).unwrap());
     
-    let _ = globals.set("sleep", lua.create_async_function(|_, milliseconds: u64| async move {
-        sleep(Duration::from_millis(milliseconds)).await;
-        Ok(())
+    let _ = globals.set("sleep", lua.create_async_function(|lua, milliseconds: u64| async move {
+        os(&lua).sleep(Duration::from_millis(milliseconds)).await
     }).unwrap());
 
-    let _ = globals.set("messageBox", lua.create_function(|_, (title, desc): (String, String)| {
-        message_box(title, desc);
+    let _ = globals.set("messageBox", lua.create_function(|lua, (title, desc): (String, String)| {
+        os(lua).message_box(&title, &desc);
 
         Ok(())
     }).unwrap());
 
-    let _ = globals.set("log", lua.create_function(|_, (msg, level, dev_mode): (String, String, bool)| {
+    let _ = globals.set("log", lua.create_function(|lua, (msg, level, dev_mode): (String, String, bool)| {
         let true_lvl = match level.as_str() {
             "Info" => output::LogLevel::Info,
             "Warning" => output::LogLevel::Warning,
@@ -61,13 +112,7 @@ fn get_custom_lua() -> Lua {
             _ => output::LogLevel::Info
         };
 
-        let dev = match dev_mode {
-            true => true,
-            false => false,
-            _ => false
-        };
-
-        output::add_log(format!("[Lua script] {}", msg), true_lvl, dev);
+        os(lua).log(&msg, true_lvl, dev_mode);
         Ok(())
     }).unwrap());
 
@@ -75,333 +120,106 @@ fn get_custom_lua() -> Lua {
         lua.create_string("Unknown".to_owned()).unwrap()
     ));
 
+    let _ = window::register(&lua);
+    let _ = saves::register(&lua);
+    let _ = fs_api::register(&lua);
+    let _ = storage::register(&lua);
+    let _ = http::register(&lua);
+    let _ = formats::register(&lua);
+    let _ = ini::register(&lua);
+    let _ = sys::register(&lua);
+    let _ = dialogs::register(&lua);
+    let _ = notify::register(&lua);
+    let _ = runs::register(&lua);
+    let _ = modules::register(&lua);
+    let _ = tasks::register(&lua);
+
     let _ = globals.set("Info", lua.create_string("Info").unwrap());
     let _ = globals.set("Warning", lua.create_string("Warning").unwrap());
     let _ = globals.set("Error", lua.create_string("Error").unwrap());
 
-    lua
-}
-
-fn open_app(_lua: &Lua, arg: String) -> mlua::Result<()> {
-    #[cfg(target_os = "windows")]
-    {
-        let escaped = arg.replace("&", "^&");
-
-        Command::new("cmd")
-            .args(["/C", "start", "", &escaped])
-            .spawn()
-            .map_err(mlua::Error::external)?;
+    if let Err(e) = modules::load_prelude(&lua) {
+        output::add_log(format!("[Loading Prelude] Failed to load the prelude: {e}"), output::LogLevel::Error, false);
     }
 
-    #[cfg(target_os = "macos")]
-    {
-        Command::new("open")
-            .arg(&arg)
-            .spawn()
-            .map_err(mlua::Error::external)?;
-    }
-
-    #[cfg(target_os = "linux")]
-    {
-        Command::new(&arg)
-            .spawn()
-            .map_err(mlua::Error::external)?;
-    }
-
-    Ok(())
+    lua
 }
-fn close_app(_lua: &Lua, process_name: String) -> mlua::Result<()> {
-    #[cfg(target_os = "windows")]
-    {
-        let escaped = process_name.replace("&", "^&");
-
-        Command::new("taskkill")
-            .args(["/IM", &escaped])
-            .output()
-            .map_err(mlua::Error::external)?;
-    }
-
-    #[cfg(any(target_os = "linux", target_os = "macos"))]
-    {
-        Command::new("pkill")
-            .arg(&process_name)
-            .output()
-            .map_err(mlua::Error::external)?;
-    }
 
-    Ok(())
+fn open_app(lua: &Lua, (arg, args): (String, Option<Vec<String>>)) -> mlua::Result<()> {
+    os(lua).open_app(&arg, &args.unwrap_or_default())
 }
-fn force_close_app(_lua: &Lua, process_name: String) -> mlua::Result<()> {
-    #[cfg(target_os = "windows")]
-    {
-        let escaped = process_name.replace("&", "^&");
-
-        Command::new("taskkill")
-            .args(["/IM", &escaped, "/F"])
-            .output()
-            .map_err(mlua::Error::external)?;
-    }
-
-    #[cfg(any(target_os = "linux", target_os = "macos"))]
-    {
-        Command::new("pkill")
-            .args(["-9", &process_name])
-            .output()
-            .map_err(mlua::Error::external)?;
-    }
-
-    Ok(())
+fn close_app(lua: &Lua, process_name: String) -> mlua::Result<()> {
+    os(lua).close_app(&process_name, false)
 }
-fn is_app_open(_lua: &Lua, process_name: String) -> mlua::Result<bool> {
-    #[cfg(target_os = "windows")]
-    {
-        let escaped = process_name.replace("&", "^&");
-
-        let output = Command::new("tasklist")
-            .arg("/FI")
-            .arg(format!("IMAGENAME eq {}", escaped))
-            .output()
-            .map_err(mlua::Error::external)?;
-
-        let stdout = String::from_utf8_lossy(&output.stdout).to_string();
-        Ok(stdout.contains(&process_name))
-    }
-
-    #[cfg(any(target_os = "linux", target_os = "macos"))]
-    {
-        let status = Command::new("pgrep")
-            .arg("-f")
-            .arg(&process_name)
-            .status()
-            .map_err(mlua::Error::external)?;
-
-        Ok(status.success())
-    }
+fn force_close_app(lua: &Lua, process_name: String) -> mlua::Result<()> {
+    os(lua).close_app(&process_name, true)
 }
-fn is_window_open(_lua: &Lua, window_title: String) -> mlua::Result<bool> {
-    #[cfg(target_os = "windows")]
-    {
-        use std::ffi::CString;
-        use std::ptr::null_mut;
-
-        let c_title = CString::new(window_title.clone()).map_err(mlua::Error::external)?;
-        let hwnd = unsafe { FindWindowA(null_mut(), c_title.as_ptr()) };
-
-        Ok(!hwnd.is_null())
-    }
-
-    #[cfg(target_os = "macos")]
-    {
-        let output = Command::new("osascript")
-            .arg("-e")
-            .arg(format!(
-                r#"tell application "System Events" to get the name of every window of every process"#,
-            ))
-            .output()
-            .map_err(mlua::Error::external)?;
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        Ok(stdout.contains(&window_title))
-    }
-
-    #[cfg(target_os = "linux")]
-    {
-        let output = Command::new("xdotool")
-            .args(["search", "--name", &window_title])
-            .output()
-            .map_err(mlua::Error::external)?;
-
-        Ok(!String::from_utf8_lossy(&output.stdout).trim().is_empty())
-    }
+fn is_app_open(lua: &Lua, (process_name, options): (String, Option<LuaValue>)) -> mlua::Result<LuaValue> {
+    let lookup = Lookup::from_lua(lua, LookupKind::App, &process_name, options)?;
+    lookup.result(lua, lookup.find(&*os(lua))?)
 }
-fn open_url(_lua: &Lua, url: String) -> mlua::Result<bool> {
-    #[cfg(target_os = "windows")]
-    {
-        let escaped = url.replace("&", "^&");
-
-        Command::new("cmd")
-            .args(["/C", "start", "", &escaped])
-            .spawn()
-            .map_err(mlua::Error::external)?;
-    }
-
-    #[cfg(target_os = "macos")]
-    {
-        Command::new("open")
-            .arg(&url)
-            .spawn()
-            .map_err(mlua::Error::external)?;
-    }
-
-    #[cfg(target_os = "linux")]
-    {
-        Command::new("xdg-open")
-            .arg(&url)
-            .spawn()
-            .map_err(mlua::Error::external)?;
-    }
-
-    Ok(true)
-}
-fn check_window_open(window_title: &str) -> std::io::Result<bool> {
-    #[cfg(target_os = "windows")]
-    {
-        let c_title = CString::new(window_title).unwrap();
-        let hwnd = unsafe { FindWindowA(null_mut(), c_title.as_ptr()) };
-        Ok(!hwnd.is_null())
-    }
-
-    #[cfg(target_os = "macos")]
-    {
-        let output = Command::new("osascript")
-            .arg("-e")
-            .arg(r#"tell application "System Events" to get the name of every window of every process"#)
-            .output()?;
-
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        Ok(stdout.contains(window_title))
-    }
-
-    #[cfg(target_os = "linux")]
-    {
-        let output = Command::new("xdotool")
-            .args(["search", "--name", window_title])
-            .output()?;
-
-        Ok(!String::from_utf8_lossy(&output.stdout).trim().is_empty())
-    }
+fn is_window_open(lua: &Lua, (window_title, options): (String, Option<LuaValue>)) -> mlua::Result<LuaValue> {
+    let lookup = Lookup::from_lua(lua, LookupKind::Window, &window_title, options)?;
+    lookup.result(lua, lookup.find(&*os(lua))?)
 }
-async fn wait_until_window_closed_async(window_title: String) -> LuaResult<()> {
-    loop {
-        let open = check_window_open(&window_title)
-            .map_err(mlua::Error::external)?;
+fn open_url(lua: &Lua, url: String) -> mlua::Result<bool> {
+    os(lua).open_url(&url)?;
 
-        if !open {
-            break;
-        }
-        sleep(Duration::from_secs(1)).await;
-    }
-    Ok(())
+    Ok(true)
 }
-async fn wait_until_app_closed_async(process_name: String) -> LuaResult<()> {
+/// Returns what was open before it closed, if anything was.
+async fn wait_until_closed_async(os: Arc<dyn OsLayer>, lookup: &Lookup, binding: &str) -> LuaResult<Option<Found>> {
+    let mut last = None;
     loop {
-        let running = {
-            #[cfg(target_os = "windows")]
-            {
-                let escaped = process_name.replace("&", "^&");
-
-                let output = Command::new("tasklist")
-                    .arg("/FI")
-                    .arg(format!("IMAGENAME eq {}", &escaped))
-                    .output()
-                    .map_err(|e| LuaError::external(e))?;
-
-                let stdout = String::from_utf8_lossy(&output.stdout);
-                stdout.contains(&process_name)
-            }
-            #[cfg(any(target_os = "linux", target_os = "macos"))]
-            {
-                let status = Command::new("pgrep")
-                    .arg("-f")
-                    .arg(&process_name)
-                    .status()
-                    .map_err(|e| LuaError::external(e))?;
-
-                status.success()
-            }
-        };
-
-        if !running {
-            break;
+        match lookup.find(&*os)? {
+            Some(found) => last = Some(found),
+            None => break,
         }
-
-        sleep(Duration::from_secs(1)).await;
-    }
-    Ok(())
-}
-fn is_window_open_util(title: &str) -> std::io::Result<bool> {
-    #[cfg(target_os = "windows")] {
-        let c_title = CString::new(title).unwrap();
-        let hwnd = unsafe { FindWindowA(null_mut(), c_title.as_ptr()) };
-        Ok(!hwnd.is_null())
-    }
-    #[cfg(target_os = "macos")] {
-        let out = Command::new("osascript")
-            .args([
-              "-e",
-              r#"tell application "System Events" to get the name of every window of every process"#,
-            ])
-            .output()?;
-        Ok(String::from_utf8_lossy(&out.stdout).contains(title))
-    }
-    #[cfg(target_os = "linux")] {
-        let out = Command::new("xdotool")
-            .args(["search", "--name", title])
-            .output()?;
-        Ok(!String::from_utf8_lossy(&out.stdout).trim().is_empty())
+        os.sleep(Duration::from_secs(1)).await?;
     }
+    os.note(binding, lookup.name());
+    Ok(last)
 }
-fn is_process_running(name: &str) -> std::io::Result<bool> {
-    #[cfg(target_os = "windows")] {
-        let escaped = name.replace("&", "^&");
-
-        let output = Command::new("tasklist")
-            .args(["/FI", &format!("IMAGENAME eq {}", escaped)])
-            .output()?;
-        Ok(String::from_utf8_lossy(&output.stdout).contains(&escaped))
-    }
-    #[cfg(any(target_os = "linux", target_os = "macos"))] {
-        let status = Command::new("pgrep")
-            .args(["-f", name])
-            .status()?;
-        Ok(status.success())
-    }
-}
-async fn wait_app_opened_async(name: String, timeout_ms: u64) -> LuaResult<bool> {
+async fn wait_opened_async(os: Arc<dyn OsLayer>, lookup: &Lookup, timeout_ms: u64, binding: &str) -> LuaResult<Option<Found>> {
     let mut waited = 0;
     let interval = 100;
     while waited < timeout_ms {
-        if let Ok(true) = is_process_running(&name) {
-            return Ok(true);
+        if let Some(found) = lookup.find(&*os)? {
+            os.note(binding, &format!("{} -> true", lookup.name()));
+            finished_waiting(&*os, lookup, waited, true).await?;
+            return Ok(Some(found));
         }
-        sleep(Duration::from_millis(interval)).await;
+        os.sleep(Duration::from_millis(interval)).await?;
         waited += interval;
     }
-    Ok(false)
+    os.note(binding, &format!("{} -> false", lookup.name()));
+    finished_waiting(&*os, lookup, waited, false).await?;
+    Ok(None)
 }
-async fn wait_window_opened_async(title: String, timeout_ms: u64) -> LuaResult<bool> {
-    let mut waited = 0;
-    let interval = 100;
-    while waited < timeout_ms {
-        if let Ok(true) = is_window_open_util(&title) {
-            return Ok(true);
-        }
-        sleep(Duration::from_millis(interval)).await;
-        waited += interval;
+/// Waits longer than this get a notification when they end, since the user has probably gone
+/// to do something else by then.
+const LONG_WAIT_MS: u64 = 30_000;
+async fn finished_waiting(os: &dyn OsLayer, lookup: &Lookup, waited_ms: u64, found: bool) -> LuaResult<()> {
+    if waited_ms < LONG_WAIT_MS {
+        return Ok(());
     }
-    Ok(false)
+
+    let body = if found {
+        format!("{} is open", lookup.name())
+    } else {
+        format!("Gave up waiting for {} after {} seconds", lookup.name(), waited_ms / 1000)
+    };
+
+    os.notify(Notification::new("Finished waiting", body)).await?;
+    Ok(())
 }
-fn exit(_lua: &Lua, arg: Option<u64>) -> mlua::Result<()> {
+fn exit(lua: &Lua, arg: Option<u64>) -> mlua::Result<()> {
     let err_code = arg.unwrap_or(0)
                             .try_into().unwrap_or(1);
 
-    match files::load_settings() {
-        Ok(Some(settings)) => {
-            if settings.close {
-                std::process::exit(err_code);
-            }
-        }
-        Ok(None) => {
-            println!("Settings not found.");
-        }
-        Err(e) => {
-            return Err(LuaError::external(format!("Failed to load settings: {}", e)));
-        }
-    }
-
-    Ok(())
+    os(lua).exit(err_code)
 }
-fn system() -> String {
+pub(crate) fn system() -> String {
     #[cfg(target_os = "windows")]
     {
         return "Windows".to_owned()
@@ -417,43 +235,48 @@ fn system() -> String {
         return "Linux".to_owned()
     }
 }
-fn run_command(cmd: String) {
-    let _ = Command::new("cmd")
-        .args(&["/C", &cmd])
-        .status()
-        .expect("Failed to execute command");
-}
-fn command(lua: &Lua, cmd: String) -> mlua::Result<()> {
-    match files::load_settings() {
-        Ok(Some(settings)) => {
-            if settings.dev {
-                run_command(cmd);
-            } else {
-                message_box("Developer mod off".to_owned(), "Script attempted to run \"runCommand\" on your computer without Developer mod on. For your safety, the script wasn't allowed to run their command.".to_owned());
-                return exit(lua, Some(99));
-            }
+async fn command(lua: &Lua, cmd: LuaValue, options: Option<LuaValue>) -> mlua::Result<LuaValue> {
+    let os = os(lua);
+    let request = CommandRequest::from_lua(lua, cmd, options)?;
+
+    match os.dev_mode()? {
+        Some(true) => {
+            let output = os.run_command(request).await?;
+            return output.to_lua(lua).map(LuaValue::Table);
         }
-        Ok(None) => {
-            println!("Settings not found.");
+        Some(false) => {
+            os.message_box("Developer mod off", "Script attempted to run \"runCommand\" on your computer without Developer mod on. For your safety, the script wasn't allowed to run their command.");
+            exit(lua, Some(99))?;
         }
-        Err(e) => {
-            return Err(LuaError::external(format!("Failed to load settings: {}", e)));
+        None => {
+            println!("Settings not found.");
         }
     }
 
-    Ok(())
-}
-fn message_box(title: String, desc: String) {
-    MessageDialog::new()
-        .set_title(title)
-        .set_description(desc)
-        .set_buttons(rfd::MessageButtons::Ok)
-        .show();
+    Ok(LuaValue::Nil)
 }
+pub(crate) fn lua_quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+
+    for c in text.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            // Three digits, so a digit after it stays its own character.
+            '\0' => quoted.push_str("\\000"),
+            _ => quoted.push(c),
+        }
+    }
 
-pub(crate) async fn lua_run_game(script_name: &str) -> Result<(), Box<dyn std::error::Error>> {
-    let lua = get_custom_lua();
+    quoted.push('"');
+    quoted
+}
 
+pub(crate) fn script_path(script_name: &str) -> PathBuf {
     let mut path: PathBuf = get_scripts_dir().expect("Scripts dir not found");
 
     if script_name.ends_with(".lua") {
@@ -462,15 +285,63 @@ pub(crate) async fn lua_run_game(script_name: &str) -> Result<(), Box<dyn std::e
         path.push(format!("{}.lua", script_name));
     }
 
-    let chunk = lua.load(
-        files::get_file_content(path.to_string_lossy().to_string())
-    );
+    path
+}
+
+/// Runs a game's script with its hooks and save backups, like clicking it does, but leaves the
+/// launcher open whatever the `close` setting says.
+pub(crate) async fn run_script(script_name: &str) -> hooks::Session {
+    let run = runs::start(script_name);
+    saves::auto_backup(script_name, "before_launch").await;
+    let session = hooks::run_with_hooks(script_name, Arc::new(DeferredExit(Arc::new(RealOs))), run.cancelled()).await;
+    saves::auto_backup(script_name, "after_exit").await;
+
+    drop(run);
+
+    println!("[{script_name}] {session:?}");
+
+    if session.outcome == "failed" {
+        let reason = match (&session.error, session.exit_code) {
+            (Some(error), _) => error.clone(),
+            (None, code) => format!("It exited with code {}", code.unwrap_or_default()),
+        };
+
+        notify::show(Notification::new(format!("{script_name} failed"), reason)).await;
+    }
+
+    session
+}
+
+/// Runs a game's script the way the user asked for it, so it counts as recently played.
+pub(crate) async fn launch_game(script_name: &str) -> hooks::Session {
+    if let Err(e) = files::add_recent(script_name) {
+        output::add_log(format!("[Running Game] Failed to add {script_name} to the recently played games: {e}"), output::LogLevel::Warning, true);
+    }
+
+    run_script(script_name).await
+}
 
-    let res = chunk.eval_async::<Value>().await?;
+pub(crate) async fn lua_run_game(script_name: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let session = launch_game(script_name).await;
 
-    println!("[{script_name}] {res:?}");
+    if session.exit_code.is_none() {
+        return Err(session.error.unwrap_or_default().into());
+    }
 
-    let _ = exit(&lua, Some(0));
+    RealOs.exit(session.exit_code.unwrap_or(0))?;
 
     Ok(())
 }
+
+pub(crate) async fn lua_simulate_game(script_name: &str, scenario: simulate::Scenario) -> Result<Vec<simulate::TraceEntry>, Box<dyn std::error::Error>> {
+    let mock = Arc::new(simulate::MockOs::new(scenario));
+
+    let session = hooks::run_with_hooks(script_name, mock.clone(), std::future::pending()).await;
+
+    let result = match session.exit_code {
+        Some(_) => Ok(()),
+        None => Err(session.error.unwrap_or_default()),
+    };
+
+    Ok(mock.finish(result))
+}
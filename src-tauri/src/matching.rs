@@ -0,0 +1,158 @@
+use mlua::prelude::*;
+use regex::{Regex, RegexBuilder};
+use serde::{Serialize, Deserialize};
+
+use crate::os_layer::OsLayer;
+use crate::process::ProcessInfo;
+use crate::window::WindowInfo;
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MatchMode {
+    Exact,
+    Substring,
+    Glob,
+    Regex,
+}
+
+/// The options table the lookup bindings take, like `{ match = "glob", ignoreCase = true }`.
+#[derive(Deserialize, Default, Debug)]
+#[serde(default, rename_all = "camelCase")]
+pub(crate) struct MatchOptions {
+    #[serde(rename = "match")]
+    pub(crate) mode: Option<MatchMode>,
+    pub(crate) ignore_case: bool,
+    /// Match apps against their whole command line instead of just the process name.
+    pub(crate) command_line: bool,
+}
+
+pub(crate) struct Matcher {
+    pattern: String,
+    mode: MatchMode,
+    ignore_case: bool,
+    regex: Option<Regex>,
+}
+
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str(".*"),
+            '?' => regex.push('.'),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+
+    regex.push('$');
+    regex
+}
+
+impl Matcher {
+    pub(crate) fn new(pattern: &str, mode: MatchMode, ignore_case: bool) -> Result<Self, String> {
+        let regex = match mode {
+            MatchMode::Glob => Some(glob_to_regex(pattern)),
+            MatchMode::Regex => Some(pattern.to_owned()),
+            MatchMode::Exact | MatchMode::Substring => None,
+        };
+
+        let regex = regex
+            .map(|source| RegexBuilder::new(&source).case_insensitive(ignore_case).build())
+            .transpose()
+            .map_err(|e| format!("Invalid pattern '{pattern}': {e}"))?;
+
+        Ok(Matcher { pattern: pattern.to_owned(), mode, ignore_case, regex })
+    }
+
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        if let Some(regex) = &self.regex {
+            return regex.is_match(text);
+        }
+
+        let (pattern, text) = if self.ignore_case {
+            (self.pattern.to_lowercase(), text.to_lowercase())
+        } else {
+            (self.pattern.clone(), text.to_owned())
+        };
+
+        match self.mode {
+            MatchMode::Exact => text == pattern,
+            _ => text.contains(&pattern),
+        }
+    }
+
+    pub(crate) fn pattern(&self) -> &str {
+        &self.pattern
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) enum LookupKind {
+    App,
+    Window,
+}
+
+/// Whatever a lookup found, handed back to scripts that passed options.
+#[derive(Serialize, Clone, Debug)]
+#[serde(untagged)]
+pub(crate) enum Found {
+    App(ProcessInfo),
+    Window(WindowInfo),
+}
+
+/// A compiled app or window lookup as used by `isAppOpen`, `isWindowOpen` and the wait functions.
+/// Without options, apps match their exact process name and windows any title containing the name.
+pub(crate) struct Lookup {
+    kind: LookupKind,
+    matcher: Matcher,
+    command_line: bool,
+    /// Scripts that pass options get the matched app or window back instead of a bool.
+    pub(crate) wants_object: bool,
+}
+
+impl Lookup {
+    pub(crate) fn from_lua(lua: &Lua, kind: LookupKind, name: &str, options: Option<LuaValue>) -> LuaResult<Self> {
+        let wants_object = options.as_ref().is_some_and(|o| !o.is_nil());
+
+        let options: MatchOptions = match options {
+            Some(value) if !value.is_nil() => lua.from_value(value)?,
+            _ => MatchOptions::default(),
+        };
+
+        let default_mode = match kind {
+            LookupKind::App => MatchMode::Exact,
+            LookupKind::Window => MatchMode::Substring,
+        };
+
+        let matcher = Matcher::new(name, options.mode.unwrap_or(default_mode), options.ignore_case)
+            .map_err(LuaError::external)?;
+
+        Ok(Lookup { kind, matcher, command_line: options.command_line, wants_object })
+    }
+
+    pub(crate) fn name(&self) -> &str {
+        self.matcher.pattern()
+    }
+
+    pub(crate) fn find(&self, os: &dyn OsLayer) -> LuaResult<Option<Found>> {
+        match self.kind {
+            LookupKind::App => Ok(os.list_processes()?
+                .into_iter()
+                .find(|p| self.matcher.is_match(if self.command_line { &p.command } else { &p.name }))
+                .map(Found::App)),
+            LookupKind::Window => Ok(os.list_windows()?
+                .into_iter()
+                .find(|w| self.matcher.is_match(&w.title))
+                .map(Found::Window)),
+        }
+    }
+
+    /// A bool for plain calls, or the match (nil when nothing matched) when options were given.
+    pub(crate) fn result(&self, lua: &Lua, found: Option<Found>) -> LuaResult<LuaValue> {
+        match (self.wants_object, found) {
+            (true, Some(found)) => lua.to_value(&found),
+            (true, None) => Ok(LuaValue::Nil),
+            (false, found) => Ok(LuaValue::Boolean(found.is_some())),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use mlua::prelude::*;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::files::get_lib_dir;
+
+/// Goes up whenever the bindings or the prelude change in a way scripts could notice, so a
+/// script can check for what it needs with `requireApi`.
+pub(crate) const API_VERSION: u32 = 2;
+
+const PRELUDE: &str = include_str!("../lua/prelude.lua");
+
+/// Modules that ship with the launcher. They're all under `std.` so they can't clash with the user's.
+const BUNDLED: &[(&str, &str)] = &[
+    ("std.strings", include_str!("../lua/std/strings.lua")),
+    ("std.tables", include_str!("../lua/std/tables.lua")),
+];
+
+/// `vpn.helpers` is `lib/vpn/helpers.lua` or `lib/vpn/helpers/init.lua`. Names that could
+/// point outside of `lib`, like ones with slashes or empty parts, are never found.
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    if !name.split('.').all(|part| !part.is_empty() && !part.contains(['/', '\\'])) {
+        return None;
+    }
+
+    let relative = name.replace('.', "/");
+
+    [dir.join(format!("{relative}.lua")), dir.join(&relative).join("init.lua")]
+        .into_iter()
+        .find(|path| path.is_file())
+}
+
+/// Swaps Lua's searchers for one that looks in the bundled modules and then `scripts/lib`,
+/// instead of the launcher's working directory. `require` still caches modules in
+/// `package.loaded`, so each is loaded once per Lua state, and a module that ends up
+/// requiring itself is an error rather than a stack overflow.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    register_with(lua, get_lib_dir())
+}
+
+fn register_with(lua: &Lua, lib_dir: Option<PathBuf>) -> LuaResult<()> {
+    let package: LuaTable = lua.globals().get("package")?;
+    let searchers: LuaTable = package.get("searchers")?;
+    let preload: LuaFunction = searchers.get(1)?;
+
+    // Modules being loaded right now, innermost last.
+    let loading: Arc<Mutex<Vec<String>>> = Arc::new(Mutex::new(Vec::new()));
+
+    let searcher = lua.create_function(move |lua, name: String| {
+        let cycle = {
+            let loading = loading.lock().unwrap();
+            loading.iter().position(|loaded| *loaded == name).map(|start| loading[start..].to_vec())
+        };
+
+        if let Some(mut chain) = cycle {
+            chain.push(name);
+            return Err(LuaError::external(format!("Modules require each other in a loop: {}", chain.join(" -> "))));
+        }
+
+        let (code, chunk_name, origin) = if let Some((_, code)) = BUNDLED.iter().find(|(bundled, _)| *bundled == name) {
+            (code.to_string(), name.clone(), name.clone())
+        } else if let Some(path) = lib_dir.as_deref().and_then(|dir| find_file(dir, &name)) {
+            let code = std::fs::read_to_string(&path)
+                .map_err(|e| LuaError::external(format!("Failed to read module '{name}' from {}: {e}", path.display())))?;
+
+            (code, format!("lib/{}", name.replace('.', "/")), path.to_string_lossy().to_string())
+        } else {
+            let dir = lib_dir.as_ref().map(|dir| dir.display().to_string()).unwrap_or_else(|| "lib".to_owned());
+            return format!("no module '{name}' in {dir}").into_lua_multi(lua);
+        };
+
+        let chunk = lua.load(code).set_name(chunk_name).into_function()?;
+        let loading = loading.clone();
+
+        let loader = lua.create_function(move |_, args: LuaMultiValue| {
+            loading.lock().unwrap().push(name.clone());
+            let result = chunk.call::<LuaValue>(args);
+            loading.lock().unwrap().pop();
+
+            result
+        })?;
+
+        (loader, origin).into_lua_multi(lua)
+    })?;
+
+    package.set("searchers", lua.create_sequence_from([preload, searcher])?)?;
+    package.set("path", "")?;
+    package.set("cpath", "")?;
+
+    Ok(())
+}
+
+/// Sets `apiVersion` and `launcherVersion` and runs the prelude. It uses the other bindings,
+/// so it has to be loaded last.
+pub(crate) fn load_prelude(lua: &Lua) -> LuaResult<()> {
+    let globals = lua.globals();
+
+    globals.set("apiVersion", API_VERSION)?;
+    globals.set("launcherVersion", env!("CARGO_PKG_VERSION"))?;
+
+    lua.load(PRELUDE).set_name("prelude").exec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lua_with_lib(test: &str, files: &[(&str, &str)]) -> (Lua, PathBuf) {
+        let dir = std::env::temp_dir().join(format!("luauncher-{test}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        for (path, code) in files {
+            let path = dir.join(path);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, code).unwrap();
+        }
+
+        let lua = Lua::new();
+        register_with(&lua, Some(dir.clone())).unwrap();
+        (lua, dir)
+    }
+
+    #[test]
+    fn modules_come_from_lib_and_std() {
+        let (lua, dir) = lua_with_lib("lib", &[
+            ("vpn/helpers.lua", "return { name = 'helpers' }"),
+            ("tools/init.lua", "return 'from init'"),
+            ("counter.lua", "loads = (loads or 0) + 1 return {}"),
+        ]);
+
+        let (name, init, trimmed, found): (String, String, String, bool) = lua.load(r#"
+            return require("vpn.helpers").name, require("tools"),
+                require("std.strings").trim("  a  "), require("std.tables").contains({ 1, 2 }, 2)
+        "#).eval().unwrap();
+
+        assert_eq!(name, "helpers");
+        assert_eq!(init, "from init");
+        assert_eq!(trimmed, "a");
+        assert!(found);
+
+        let (same, loads): (bool, i64) = lua.load(r#"return require("counter") == require("counter"), loads"#).eval().unwrap();
+
+        assert!(same, "require should give back the table in package.loaded");
+        assert_eq!(loads, 1);
+
+        let missing = lua.load(r#"require("vpn/helpers")"#).exec().unwrap_err().to_string();
+        assert!(missing.contains("no module 'vpn/helpers'"), "{missing}");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn modules_requiring_each_other_are_an_error() {
+        let (lua, dir) = lua_with_lib("cycle", &[
+            ("a.lua", "return require('b')"),
+            ("b.lua", "return require('a')"),
+            ("c.lua", "return 'c'"),
+        ]);
+
+        let error = lua.load(r#"require("a")"#).exec().unwrap_err().to_string();
+        assert!(error.contains("Modules require each other in a loop: a -> b -> a"), "{error}");
+
+        // The chain is unwound, so modules can still be loaded after the error.
+        assert_eq!(lua.load(r#"return require("c")"#).eval::<String>().unwrap(), "c");
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
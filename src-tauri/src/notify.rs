@@ -0,0 +1,136 @@
+use mlua::prelude::*;
+use serde::Deserialize;
+use std::time::Duration;
+
+use crate::os_layer::os;
+use crate::output;
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Urgency {
+    Low,
+    #[default]
+    Normal,
+    Critical,
+}
+
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Notification {
+    pub(crate) title: String,
+    pub(crate) body: String,
+    pub(crate) urgency: Urgency,
+    /// How long it stays up. `None` leaves it to the system.
+    pub(crate) timeout: Option<Duration>,
+    /// A path to an image or the name of an icon from the system's theme.
+    pub(crate) icon: Option<String>,
+}
+
+impl Notification {
+    pub(crate) fn new(title: impl Into<String>, body: impl Into<String>) -> Self {
+        Notification { title: title.into(), body: body.into(), ..Default::default() }
+    }
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct NotifyOptions {
+    urgency: Urgency,
+    /// In milliseconds.
+    timeout: Option<u64>,
+    icon: Option<String>,
+}
+
+/// Goes through the freedesktop notification service over D-Bus, which fails when nothing
+/// on the session bus owns it.
+#[cfg(target_os = "linux")]
+async fn send(notification: &Notification) -> Result<(), String> {
+    use std::collections::HashMap;
+    use zbus::zvariant::Value;
+
+    let connection = zbus::Connection::session().await.map_err(|e| e.to_string())?;
+
+    let urgency: u8 = match notification.urgency {
+        Urgency::Low => 0,
+        Urgency::Normal => 1,
+        Urgency::Critical => 2,
+    };
+    let hints = HashMap::from([("urgency", Value::U8(urgency))]);
+    let timeout = notification.timeout.map(|t| t.as_millis().min(i32::MAX as u128) as i32).unwrap_or(-1);
+
+    connection.call_method(
+        Some("org.freedesktop.Notifications"),
+        "/org/freedesktop/Notifications",
+        Some("org.freedesktop.Notifications"),
+        "Notify",
+        &(
+            "Luauncher",
+            0u32,
+            notification.icon.as_deref().unwrap_or(""),
+            notification.title.as_str(),
+            notification.body.as_str(),
+            Vec::<&str>::new(),
+            hints,
+            timeout,
+        ),
+    ).await.map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+async fn send(notification: &Notification) -> Result<(), String> {
+    let mut toast = notify_rust::Notification::new();
+    toast.appname("Luauncher").summary(&notification.title).body(&notification.body);
+
+    if let Some(icon) = &notification.icon {
+        toast.icon(icon);
+    }
+    if let Some(timeout) = notification.timeout {
+        toast.timeout(notify_rust::Timeout::Milliseconds(timeout.as_millis().min(u32::MAX as u128) as u32));
+    }
+
+    tokio::task::spawn_blocking(move || toast.show().map(|_| ()))
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())
+}
+
+/// Shows `notification`, or only logs it if the system can't. Returns whether it was shown.
+pub(crate) async fn show(notification: Notification) -> bool {
+    match send(&notification).await {
+        Ok(()) => true,
+        Err(e) => {
+            output::add_log(format!("[Notification] {}: {}", notification.title, notification.body), output::LogLevel::Info, false);
+            output::add_log(format!("[Notification] Couldn't show a notification, so it was only logged: {e}"), output::LogLevel::Warning, true);
+            false
+        }
+    }
+}
+
+/// For the launcher's own notifications, which nothing waits on.
+pub(crate) fn launcher(title: impl Into<String>, body: impl Into<String>) {
+    let notification = Notification::new(title, body);
+
+    tokio::spawn(async move {
+        show(notification).await;
+    });
+}
+
+/// Builds `notify`. Returns whether the notification was shown, since it's only logged when
+/// the system has nowhere to show it.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    lua.globals().set("notify", lua.create_async_function(|lua, (title, body, options): (String, Option<String>, Option<LuaValue>)| async move {
+        let options: NotifyOptions = match options {
+            Some(value) if !value.is_nil() => lua.from_value(value)?,
+            _ => NotifyOptions::default(),
+        };
+
+        os(&lua).notify(Notification {
+            title,
+            body: body.unwrap_or_default(),
+            urgency: options.urgency,
+            timeout: options.timeout.map(Duration::from_millis),
+            icon: options.icon,
+        }).await
+    })?)
+}
@@ -0,0 +1,415 @@
+use mlua::prelude::*;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::process::Command;
+use std::sync::Arc;
+use std::time::Duration;
+use rfd::MessageDialog;
+use serde_json::{Map, Value};
+
+use crate::command::{self, CommandOutput, CommandRequest};
+use crate::dialogs::{self, Dialog};
+use crate::files;
+use crate::http::{self, HttpRequest, HttpResponse, Progress};
+use crate::instance;
+use crate::notify::{self, Notification};
+use crate::output;
+use crate::process::{self, ProcessInfo};
+use crate::runs::{self, Report};
+use crate::saves::{self, Snapshot};
+use crate::simulate;
+use crate::storage;
+use crate::tray;
+use crate::window::{self, WindowAction, WindowInfo};
+
+pub(crate) type Sleep = Pin<Box<dyn Future<Output = LuaResult<()>> + Send>>;
+pub(crate) type FileChange<'a> = Box<dyn FnOnce() -> std::io::Result<()> + 'a>;
+pub(crate) type Pending<'a, T> = Pin<Box<dyn Future<Output = LuaResult<T>> + Send + 'a>>;
+
+/// Everything a script can do to the computer it runs on. The bindings in `lua_utils` only
+/// talk to the OS through this, so scripts can be run against `simulate::MockOs` instead.
+pub(crate) trait OsLayer: Send + Sync {
+    fn open_app(&self, app: &str, args: &[String]) -> LuaResult<()>;
+    fn close_app(&self, process_name: &str, force: bool) -> LuaResult<()>;
+    fn list_processes(&self) -> LuaResult<Vec<ProcessInfo>>;
+    fn list_windows(&self) -> LuaResult<Vec<WindowInfo>>;
+    fn window_action(&self, window: &WindowInfo, action: WindowAction) -> LuaResult<()>;
+    fn open_url(&self, url: &str) -> LuaResult<()>;
+    fn backup_saves(&self, game: &str) -> Pending<'static, Snapshot>;
+    fn restore_saves(&self, game: &str, id: &str) -> Pending<'static, ()>;
+    fn list_saves(&self, game: &str) -> LuaResult<Vec<Snapshot>>;
+    fn load_storage(&self, game: &str) -> LuaResult<Map<String, Value>>;
+    /// Sets `key` in the game's storage, or removes it for `None`.
+    fn change_storage(&self, game: &str, key: &str, value: Option<Value>) -> LuaResult<()>;
+    fn run_command(&self, request: CommandRequest) -> Pending<'static, CommandOutput>;
+    fn message_box(&self, title: &str, desc: &str);
+    fn dialog(&self, dialog: Dialog) -> Pending<'static, serde_json::Value>;
+    fn notify(&self, notification: Notification) -> Pending<'static, bool>;
+    fn report(&self, game: &str, report: Report);
+    fn sleep(&self, duration: Duration) -> Sleep;
+    fn exit(&self, code: i32) -> LuaResult<()>;
+    fn dev_mode(&self) -> LuaResult<Option<bool>>;
+    fn has_capability(&self, game: Option<&str>, name: &str) -> LuaResult<bool>;
+    fn http(&self, request: HttpRequest) -> Pending<'static, HttpResponse>;
+    fn download<'a>(&self, request: HttpRequest, path: PathBuf, progress: Progress<'a>) -> Pending<'a, u64>;
+
+    fn log(&self, message: &str, level: output::LogLevel, dev_mode: bool) {
+        output::add_log(format!("[Lua script] {}", message), level, dev_mode);
+    }
+
+    /// Makes a change to files for the `fs` bindings. Implementations that shouldn't touch the
+    /// disk can skip `change` and just record it.
+    fn modify_files(&self, _action: &str, _detail: &str, change: FileChange) -> LuaResult<()> {
+        change().map_err(mlua::Error::external)
+    }
+
+    /// Lets an implementation record calls that don't touch the OS themselves, like the wait functions.
+    fn note(&self, _action: &str, _detail: &str) {}
+}
+
+pub(crate) fn os(lua: &Lua) -> Arc<dyn OsLayer> {
+    lua.app_data_ref::<Arc<dyn OsLayer>>()
+        .map(|os| os.clone())
+        .unwrap_or_else(|| Arc::new(RealOs))
+}
+
+pub(crate) struct RealOs;
+
+impl OsLayer for RealOs {
+    fn open_app(&self, app: &str, args: &[String]) -> LuaResult<()> {
+        #[cfg(target_os = "windows")]
+        {
+            let escaped = app.replace("&", "^&");
+            let escaped_args: Vec<String> = args.iter().map(|a| a.replace("&", "^&")).collect();
+
+            Command::new("cmd")
+                .args(["/C", "start", "", &escaped])
+                .args(&escaped_args)
+                .spawn()
+                .map_err(mlua::Error::external)?;
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            let mut command = Command::new("open");
+            command.arg(app);
+
+            if !args.is_empty() {
+                command.arg("--args").args(args);
+            }
+
+            command.spawn()
+                .map_err(mlua::Error::external)?;
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            Command::new(app)
+                .args(args)
+                .spawn()
+                .map_err(mlua::Error::external)?;
+        }
+
+        Ok(())
+    }
+
+    fn close_app(&self, process_name: &str, force: bool) -> LuaResult<()> {
+        #[cfg(target_os = "windows")]
+        {
+            let escaped = process_name.replace("&", "^&");
+
+            let mut command = Command::new("taskkill");
+            command.args(["/IM", &escaped]);
+
+            if force {
+                command.arg("/F");
+            }
+
+            command.output()
+                .map_err(mlua::Error::external)?;
+        }
+
+        #[cfg(any(target_os = "linux", target_os = "macos"))]
+        {
+            let mut command = Command::new("pkill");
+
+            if force {
+                command.arg("-9");
+            }
+
+            command.arg(process_name)
+                .output()
+                .map_err(mlua::Error::external)?;
+        }
+
+        Ok(())
+    }
+
+    fn list_processes(&self) -> LuaResult<Vec<ProcessInfo>> {
+        process::list_processes().map_err(mlua::Error::external)
+    }
+
+    fn list_windows(&self) -> LuaResult<Vec<WindowInfo>> {
+        window::list_windows().map_err(mlua::Error::external)
+    }
+
+    fn window_action(&self, window: &WindowInfo, action: WindowAction) -> LuaResult<()> {
+        window::act(window, action).map_err(mlua::Error::external)
+    }
+
+    fn open_url(&self, url: &str) -> LuaResult<()> {
+        #[cfg(target_os = "windows")]
+        {
+            let escaped = url.replace("&", "^&");
+
+            Command::new("cmd")
+                .args(["/C", "start", "", &escaped])
+                .spawn()
+                .map_err(mlua::Error::external)?;
+        }
+
+        #[cfg(target_os = "macos")]
+        {
+            Command::new("open")
+                .arg(url)
+                .spawn()
+                .map_err(mlua::Error::external)?;
+        }
+
+        #[cfg(target_os = "linux")]
+        {
+            Command::new("xdg-open")
+                .arg(url)
+                .spawn()
+                .map_err(mlua::Error::external)?;
+        }
+
+        Ok(())
+    }
+
+    /// Hashing and copying a save folder can take a while, so it's done off the async threads.
+    fn backup_saves(&self, game: &str) -> Pending<'static, Snapshot> {
+        let game = game.to_owned();
+
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || saves::backup(&game, "script"))
+                .await
+                .map_err(LuaError::external)?
+                .map_err(LuaError::external)
+        })
+    }
+
+    fn restore_saves(&self, game: &str, id: &str) -> Pending<'static, ()> {
+        let (game, id) = (game.to_owned(), id.to_owned());
+
+        Box::pin(async move {
+            tokio::task::spawn_blocking(move || saves::restore(&game, &id))
+                .await
+                .map_err(LuaError::external)?
+                .map_err(LuaError::external)
+        })
+    }
+
+    fn list_saves(&self, game: &str) -> LuaResult<Vec<Snapshot>> {
+        saves::list(game).map_err(mlua::Error::external)
+    }
+
+    fn load_storage(&self, game: &str) -> LuaResult<Map<String, Value>> {
+        storage::load(game).map_err(mlua::Error::external)
+    }
+
+    fn change_storage(&self, game: &str, key: &str, value: Option<Value>) -> LuaResult<()> {
+        storage::update(game, |data| match value {
+            Some(value) => {
+                data.insert(key.to_owned(), value);
+            }
+            None => {
+                data.remove(key);
+            }
+        }).map_err(mlua::Error::external)
+    }
+
+    fn run_command(&self, request: CommandRequest) -> Pending<'static, CommandOutput> {
+        Box::pin(async move { command::run(request).await.map_err(LuaError::external) })
+    }
+
+    fn message_box(&self, title: &str, desc: &str) {
+        MessageDialog::new()
+            .set_title(title)
+            .set_description(desc)
+            .set_buttons(rfd::MessageButtons::Ok)
+            .show();
+    }
+
+    fn dialog(&self, dialog: Dialog) -> Pending<'static, serde_json::Value> {
+        Box::pin(async move { dialogs::ask(dialog).await.map_err(LuaError::external) })
+    }
+
+    fn notify(&self, notification: Notification) -> Pending<'static, bool> {
+        Box::pin(async move { Ok(notify::show(notification).await) })
+    }
+
+    fn report(&self, game: &str, report: Report) {
+        runs::report(game, report)
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        Box::pin(async move {
+            tokio::time::sleep(duration).await;
+            Ok(())
+        })
+    }
+
+    fn exit(&self, code: i32) -> LuaResult<()> {
+        match files::load_settings() {
+            Ok(Some(settings)) => {
+                // With the tray there's still a way back, so the window only goes out of the way.
+                if settings.close {
+                    match crate::app_handle().filter(|app| tray::is_shown(app)) {
+                        Some(app) => tray::hide(app),
+                        None => {
+                            instance::release();
+                            std::process::exit(code)
+                        }
+                    }
+                }
+            }
+            Ok(None) => {
+                println!("Settings not found.");
+            }
+            Err(e) => {
+                return Err(LuaError::external(format!("Failed to load settings: {}", e)));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn dev_mode(&self) -> LuaResult<Option<bool>> {
+        files::load_settings()
+            .map(|settings| settings.map(|s| s.dev))
+            .map_err(|e| LuaError::external(format!("Failed to load settings: {}", e)))
+    }
+
+    fn has_capability(&self, game: Option<&str>, name: &str) -> LuaResult<bool> {
+        files::has_capability(game, name)
+            .map_err(|e| LuaError::external(format!("Failed to load settings: {}", e)))
+    }
+
+    fn http(&self, request: HttpRequest) -> Pending<'static, HttpResponse> {
+        Box::pin(async move { http::send(request).await.map_err(LuaError::external) })
+    }
+
+    fn download<'a>(&self, request: HttpRequest, path: PathBuf, progress: Progress<'a>) -> Pending<'a, u64> {
+        Box::pin(async move { http::download(request, &path, progress).await })
+    }
+}
+
+/// Passes everything through to another layer except `exit`, which stops the script with
+/// `simulate::ScriptExit` instead, so the launcher can run more Lua (like hooks) afterwards.
+pub(crate) struct DeferredExit(pub(crate) Arc<dyn OsLayer>);
+
+impl OsLayer for DeferredExit {
+    fn open_app(&self, app: &str, args: &[String]) -> LuaResult<()> {
+        self.0.open_app(app, args)
+    }
+
+    fn close_app(&self, process_name: &str, force: bool) -> LuaResult<()> {
+        self.0.close_app(process_name, force)
+    }
+
+    fn list_processes(&self) -> LuaResult<Vec<ProcessInfo>> {
+        self.0.list_processes()
+    }
+
+    fn list_windows(&self) -> LuaResult<Vec<WindowInfo>> {
+        self.0.list_windows()
+    }
+
+    fn window_action(&self, window: &WindowInfo, action: WindowAction) -> LuaResult<()> {
+        self.0.window_action(window, action)
+    }
+
+    fn open_url(&self, url: &str) -> LuaResult<()> {
+        self.0.open_url(url)
+    }
+
+    fn backup_saves(&self, game: &str) -> Pending<'static, Snapshot> {
+        self.0.backup_saves(game)
+    }
+
+    fn restore_saves(&self, game: &str, id: &str) -> Pending<'static, ()> {
+        self.0.restore_saves(game, id)
+    }
+
+    fn list_saves(&self, game: &str) -> LuaResult<Vec<Snapshot>> {
+        self.0.list_saves(game)
+    }
+
+    fn load_storage(&self, game: &str) -> LuaResult<Map<String, Value>> {
+        self.0.load_storage(game)
+    }
+
+    fn change_storage(&self, game: &str, key: &str, value: Option<Value>) -> LuaResult<()> {
+        self.0.change_storage(game, key, value)
+    }
+
+    fn run_command(&self, request: CommandRequest) -> Pending<'static, CommandOutput> {
+        self.0.run_command(request)
+    }
+
+    fn message_box(&self, title: &str, desc: &str) {
+        self.0.message_box(title, desc)
+    }
+
+    fn dialog(&self, dialog: Dialog) -> Pending<'static, serde_json::Value> {
+        self.0.dialog(dialog)
+    }
+
+    fn notify(&self, notification: Notification) -> Pending<'static, bool> {
+        self.0.notify(notification)
+    }
+
+    fn report(&self, game: &str, report: Report) {
+        self.0.report(game, report)
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        self.0.sleep(duration)
+    }
+
+    fn exit(&self, code: i32) -> LuaResult<()> {
+        self.0.note("exit", &code.to_string());
+
+        Err(LuaError::external(simulate::ScriptExit(code)))
+    }
+
+    fn dev_mode(&self) -> LuaResult<Option<bool>> {
+        self.0.dev_mode()
+    }
+
+    fn has_capability(&self, game: Option<&str>, name: &str) -> LuaResult<bool> {
+        self.0.has_capability(game, name)
+    }
+
+    fn http(&self, request: HttpRequest) -> Pending<'static, HttpResponse> {
+        self.0.http(request)
+    }
+
+    fn download<'a>(&self, request: HttpRequest, path: PathBuf, progress: Progress<'a>) -> Pending<'a, u64> {
+        self.0.download(request, path, progress)
+    }
+
+    fn log(&self, message: &str, level: output::LogLevel, dev_mode: bool) {
+        self.0.log(message, level, dev_mode)
+    }
+
+    fn modify_files(&self, action: &str, detail: &str, change: FileChange) -> LuaResult<()> {
+        self.0.modify_files(action, detail, change)
+    }
+
+    fn note(&self, action: &str, detail: &str) {
+        self.0.note(action, detail)
+    }
+}
@@ -0,0 +1,120 @@
+use serde::Serialize;
+use std::io;
+#[cfg(any(target_os = "windows", target_os = "macos"))]
+use std::process::Command;
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub(crate) struct ProcessInfo {
+    pub(crate) pid: u32,
+    /// The executable's file name, like `steam` or `Steam.exe`.
+    pub(crate) name: String,
+    /// The full command line, or just the name where the OS doesn't give it out.
+    pub(crate) command: String,
+}
+
+/// The file name at the end of a path. Wine and Proton games have Windows paths, so `\` counts too.
+#[cfg(any(target_os = "linux", target_os = "macos", test))]
+fn file_name(path: &str) -> &str {
+    path.rsplit(['/', '\\']).next().unwrap_or(path)
+}
+
+#[cfg(target_os = "linux")]
+pub(crate) fn list_processes() -> io::Result<Vec<ProcessInfo>> {
+    let mut processes = Vec::new();
+
+    for entry in std::fs::read_dir("/proc")?.flatten() {
+        let Some(pid) = entry.file_name().to_str().and_then(|name| name.parse::<u32>().ok()) else {
+            continue;
+        };
+
+        // Processes can exit while we're reading, so anything unreadable is skipped.
+        let cmdline = std::fs::read(entry.path().join("cmdline")).unwrap_or_default();
+        let args: Vec<String> = cmdline.split(|b| *b == 0)
+            .filter(|arg| !arg.is_empty())
+            .map(|arg| String::from_utf8_lossy(arg).to_string())
+            .collect();
+
+        // `comm` is cut off at 15 characters, so the name comes from argv[0] when there is one.
+        let name = match args.first() {
+            Some(arg0) => file_name(arg0).to_owned(),
+            None => match std::fs::read_to_string(entry.path().join("comm")) {
+                Ok(comm) => comm.trim().to_owned(),
+                Err(_) => continue,
+            },
+        };
+
+        let command = if args.is_empty() { name.clone() } else { args.join(" ") };
+
+        processes.push(ProcessInfo { pid, name, command });
+    }
+
+    Ok(processes)
+}
+
+#[cfg(target_os = "windows")]
+pub(crate) fn list_processes() -> io::Result<Vec<ProcessInfo>> {
+    let output = Command::new("tasklist")
+        .args(["/FO", "CSV", "/NH"])
+        .output()?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    Ok(stdout.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.trim().trim_matches('"').split("\",\"").collect();
+            let name = fields.first()?.to_string();
+            let pid = fields.get(1)?.parse().ok()?;
+
+            Some(ProcessInfo { pid, command: name.clone(), name })
+        })
+        .collect())
+}
+
+#[cfg(target_os = "macos")]
+pub(crate) fn list_processes() -> io::Result<Vec<ProcessInfo>> {
+    let ps = |column: &str| -> io::Result<Vec<(u32, String)>> {
+        let output = Command::new("ps")
+            .args(["-axww", "-o", &format!("pid=,{column}=")])
+            .output()?;
+
+        Ok(String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(|line| {
+                let (pid, rest) = line.trim_start().split_once(' ')?;
+                Some((pid.parse().ok()?, rest.trim().to_owned()))
+            })
+            .collect())
+    };
+
+    // `comm` is the executable path, which can have spaces, so the arguments are asked for separately.
+    let commands: std::collections::HashMap<u32, String> = ps("args")?.into_iter().collect();
+
+    Ok(ps("comm")?
+        .into_iter()
+        .map(|(pid, path)| {
+            let name = file_name(&path).to_owned();
+            let command = commands.get(&pid).cloned().unwrap_or_else(|| path.clone());
+
+            ProcessInfo { pid, name, command }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::file_name;
+
+    #[test]
+    fn names_from_paths() {
+        let cases = [
+            ("/usr/bin/steam", "steam"),
+            ("C:\\Games\\Celeste\\Celeste.exe", "Celeste.exe"),
+            ("Z:/home/me/game.exe", "game.exe"),
+            ("steam", "steam"),
+        ];
+
+        for (path, name) in cases {
+            assert_eq!(file_name(path), name, "{path}");
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use mlua::prelude::*;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+use tauri::Emitter;
+use tokio::sync::Notify;
+
+use crate::lua_utils;
+use crate::os_layer::os;
+use crate::output;
+use crate::tray;
+
+/// What a running script last said about itself, through `progress` and `status`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Run {
+    pub(crate) id: u64,
+    pub(crate) game: String,
+    /// Unix time in seconds.
+    pub(crate) started_at: u64,
+    pub(crate) status: Option<String>,
+    /// From 0 to 1.
+    pub(crate) progress: Option<f64>,
+    #[serde(skip)]
+    cancel: Arc<Notify>,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) enum Report {
+    Progress { fraction: f64, message: Option<String> },
+    /// `None` clears both the status and the progress.
+    Status(Option<String>),
+}
+
+/// Runs by game, since that's how the library shows them. Running a game again while it's
+/// still going takes over its record.
+static RUNS: Lazy<Mutex<HashMap<String, Run>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+pub(crate) fn list() -> Vec<Run> {
+    let mut runs: Vec<Run> = RUNS.lock().unwrap().values().cloned().collect();
+    runs.sort_by_key(|run| run.id);
+    runs
+}
+
+/// Sends every run to the frontend as the `runs` event, so it never has to piece updates together.
+fn publish() {
+    let Some(app) = crate::app_handle() else {
+        return;
+    };
+
+    if let Err(e) = app.emit("runs", list()) {
+        output::add_log(format!("[Runs] Failed to send the running scripts to the window: {e}"), output::LogLevel::Warning, true);
+    }
+}
+
+/// Removes the run when its script is done, however that happens.
+pub(crate) struct RunGuard {
+    id: u64,
+    game: String,
+    cancel: Arc<Notify>,
+}
+
+impl RunGuard {
+    /// Finishes once someone cancels the run with `cancel`.
+    pub(crate) async fn cancelled(&self) {
+        self.cancel.notified().await;
+    }
+}
+
+impl Drop for RunGuard {
+    fn drop(&mut self) {
+        let mut runs = RUNS.lock().unwrap();
+
+        if runs.get(&self.game).is_some_and(|run| run.id == self.id) {
+            runs.remove(&self.game);
+            drop(runs);
+            publish();
+            tray::refresh();
+        }
+    }
+}
+
+pub(crate) fn start(game: &str) -> RunGuard {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let cancel = Arc::new(Notify::new());
+
+    RUNS.lock().unwrap().insert(game.to_owned(), Run {
+        id,
+        game: game.to_owned(),
+        started_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        status: None,
+        progress: None,
+        cancel: cancel.clone(),
+    });
+    publish();
+    tray::refresh();
+
+    RunGuard { id, game: game.to_owned(), cancel }
+}
+
+/// Stops `game`'s script. Its `after_exit.lua` hooks still run. Returns whether it was running.
+pub(crate) fn cancel(game: &str) -> bool {
+    match RUNS.lock().unwrap().get(game) {
+        Some(run) => {
+            // Keeps the wakeup if the script isn't being waited on yet.
+            run.cancel.notify_one();
+            true
+        }
+        None => false,
+    }
+}
+
+/// Updates `game`'s run. Scripts that weren't started through `start`, like the ones in tests,
+/// have nothing to update.
+pub(crate) fn report(game: &str, report: Report) {
+    {
+        let mut runs = RUNS.lock().unwrap();
+
+        let Some(run) = runs.get_mut(game) else {
+            return;
+        };
+
+        match report {
+            Report::Progress { fraction, message } => {
+                run.progress = Some(fraction.clamp(0.0, 1.0));
+
+                if message.is_some() {
+                    run.status = message;
+                }
+            }
+            Report::Status(text) => {
+                run.progress = None;
+                run.status = text;
+            }
+        }
+    }
+
+    publish();
+}
+
+fn current_game(lua: &Lua) -> LuaResult<String> {
+    Ok(lua_utils::current_game(lua)?.unwrap_or_default())
+}
+
+/// Builds `progress` and `status`, which show on the game's tile in the library while it runs.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    let globals = lua.globals();
+
+    globals.set("progress", lua.create_function(|lua, (fraction, message): (f64, Option<String>)| {
+        if !fraction.is_finite() {
+            return Err(LuaError::external("progress takes a number from 0 to 1"));
+        }
+
+        os(lua).report(&current_game(lua)?, Report::Progress { fraction, message });
+        Ok(())
+    })?)?;
+
+    globals.set("status", lua.create_function(|lua, text: Option<String>| {
+        os(lua).report(&current_game(lua)?, Report::Status(text));
+        Ok(())
+    })?)?;
+
+    Ok(())
+}
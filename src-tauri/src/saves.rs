@@ -0,0 +1,443 @@
+use mlua::prelude::*;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::files::{self, expand_home, get_app_base};
+use crate::lua_utils;
+use crate::os_layer::os;
+use crate::output;
+
+const DEFAULT_KEEP: usize = 10;
+
+/// Where a game keeps its saves and how many snapshots of them to keep, stored in `Settings.saves`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct SaveConfig {
+    /// The save folder. A leading `~` is the home folder.
+    pub(crate) path: String,
+    /// How many snapshots to keep, oldest are removed first.
+    #[serde(default = "default_keep")]
+    pub(crate) keep: usize,
+    /// Whether to take snapshots before launching and after exiting the game.
+    #[serde(default = "default_auto")]
+    pub(crate) auto: bool,
+}
+
+fn default_keep() -> usize {
+    DEFAULT_KEEP
+}
+
+fn default_auto() -> bool {
+    true
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub(crate) struct FileEntry {
+    pub(crate) hash: String,
+    pub(crate) size: u64,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct Snapshot {
+    pub(crate) id: String,
+    pub(crate) game: String,
+    /// Unix time in seconds.
+    pub(crate) created: u64,
+    /// `"before_launch"`, `"after_exit"`, `"manual"`, `"script"` or `"before_restore"`.
+    pub(crate) reason: String,
+    pub(crate) size: u64,
+    pub(crate) files: BTreeMap<String, FileEntry>,
+}
+
+/// A snapshot without its file list, which is what scripts get from `saves.list`.
+#[derive(Serialize)]
+struct SnapshotSummary {
+    id: String,
+    created: u64,
+    reason: String,
+    size: u64,
+    files: usize,
+}
+
+impl From<Snapshot> for SnapshotSummary {
+    fn from(snapshot: Snapshot) -> Self {
+        SnapshotSummary {
+            files: snapshot.files.len(),
+            id: snapshot.id,
+            created: snapshot.created,
+            reason: snapshot.reason,
+            size: snapshot.size,
+        }
+    }
+}
+
+/// The store is `saves/objects/<first 2 hex>/<sha256>` for file contents, shared by every game so
+/// unchanged files are only stored once, and `saves/<game>/<id>.json` for each snapshot.
+fn get_saves_dir() -> io::Result<PathBuf> {
+    get_app_base()
+        .map(|base| base.join("saves"))
+        .ok_or_else(|| io::Error::other("Failed to get app base directory"))
+}
+
+/// Held while the store changes. Pruning removes objects no snapshot uses yet, which would include
+/// the ones another game's backup has stored but not written its snapshot for.
+static STORE: Mutex<()> = Mutex::new(());
+
+fn lock_store() -> MutexGuard<'static, ()> {
+    STORE.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+fn object_path(store: &Path, hash: &str) -> PathBuf {
+    store.join("objects").join(&hash[..2]).join(hash)
+}
+
+pub(crate) fn get_config(game: &str) -> io::Result<Option<SaveConfig>> {
+    Ok(files::load_settings()?.and_then(|settings| settings.saves.get(game).cloned()))
+}
+
+pub(crate) fn set_config(game: &str, config: Option<SaveConfig>) -> io::Result<()> {
+    let mut settings = files::load_settings()?
+        .ok_or_else(|| io::Error::other("Settings not found"))?;
+
+    match config {
+        Some(config) => settings.saves.insert(game.to_owned(), config),
+        None => settings.saves.remove(game),
+    };
+
+    files::save_settings(&settings)
+}
+
+fn require_config(game: &str) -> io::Result<SaveConfig> {
+    get_config(game)?.ok_or_else(|| io::Error::other(format!("No save folder is set for '{game}'")))
+}
+
+fn hash_file(path: &Path) -> io::Result<(String, u64)> {
+    let mut hasher = Sha256::new();
+    let size = io::copy(&mut File::open(path)?, &mut hasher)?;
+
+    let hash = hasher.finalize().iter().map(|b| format!("{b:02x}")).collect();
+    Ok((hash, size))
+}
+
+fn walk(root: &Path, dir: &Path, found: &mut Vec<(String, PathBuf)>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+
+        if path.is_dir() {
+            walk(root, &path, found)?;
+        } else if path.is_file() {
+            let relative = path.strip_prefix(root)
+                .map_err(io::Error::other)?
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy().to_string())
+                .collect::<Vec<_>>()
+                .join("/");
+
+            found.push((relative, path));
+        }
+    }
+
+    Ok(())
+}
+
+/// Copies `source` into the store under `hash`, unless the store already has it.
+fn store_object(store: &Path, hash: &str, source: &Path) -> io::Result<()> {
+    let target = object_path(store, hash);
+
+    if target.exists() {
+        return Ok(());
+    }
+
+    fs::create_dir_all(target.parent().unwrap_or(store))?;
+
+    // Copied next to the target first so a half-written object never has a valid name.
+    let temp = target.with_extension("tmp");
+    fs::copy(source, &temp)?;
+    fs::rename(&temp, &target)
+}
+
+fn read_snapshot(path: &Path) -> io::Result<Snapshot> {
+    serde_json::from_str(&fs::read_to_string(path)?).map_err(io::Error::other)
+}
+
+pub(crate) fn list(game: &str) -> io::Result<Vec<Snapshot>> {
+    let dir = get_saves_dir()?.join(game);
+
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots: Vec<Snapshot> = fs::read_dir(&dir)?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("json"))
+        .filter_map(|path| read_snapshot(&path).ok())
+        .collect();
+
+    snapshots.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(snapshots)
+}
+
+/// Whether `id` looks like one `new_id` made: the time in milliseconds, maybe with a counter.
+fn is_id(id: &str) -> bool {
+    let (millis, n) = id.split_once('-').unwrap_or((id, "1"));
+    let digits = |text: &str| !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit());
+
+    millis.len() == 15 && digits(millis) && digits(n)
+}
+
+fn new_id(dir: &Path) -> String {
+    let millis = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis()).unwrap_or(0);
+    let mut id = format!("{millis:015}");
+    let mut n = 1;
+
+    while dir.join(format!("{id}.json")).exists() {
+        id = format!("{millis:015}-{n}");
+        n += 1;
+    }
+
+    id
+}
+
+/// Takes a snapshot of the game's save folder. If nothing changed since the last snapshot,
+/// that one is returned instead of storing the same thing again.
+pub(crate) fn backup(game: &str, reason: &str) -> io::Result<Snapshot> {
+    let _store = lock_store();
+    take_snapshot(game, reason)
+}
+
+fn take_snapshot(game: &str, reason: &str) -> io::Result<Snapshot> {
+    let config = require_config(game)?;
+    let source = expand_home(&config.path);
+
+    if !source.is_dir() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("Save folder '{}' doesn't exist", source.display())));
+    }
+
+    let store = get_saves_dir()?;
+    let dir = store.join(game);
+    fs::create_dir_all(&dir)?;
+
+    let mut found = Vec::new();
+    walk(&source, &source, &mut found)?;
+
+    let mut entries = BTreeMap::new();
+
+    for (relative, path) in found {
+        let (hash, size) = hash_file(&path)?;
+        store_object(&store, &hash, &path)?;
+        entries.insert(relative, FileEntry { hash, size });
+    }
+
+    if let Some(latest) = list(game)?.pop() {
+        if latest.files == entries {
+            return Ok(latest);
+        }
+    }
+
+    let snapshot = Snapshot {
+        id: new_id(&dir),
+        game: game.to_owned(),
+        created: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0),
+        reason: reason.to_owned(),
+        size: entries.values().map(|entry| entry.size).sum(),
+        files: entries,
+    };
+
+    let json = serde_json::to_string_pretty(&snapshot).map_err(io::Error::other)?;
+    files::write_file(dir.join(format!("{}.json", snapshot.id)), &json)?;
+
+    prune(game, config.keep)?;
+
+    Ok(snapshot)
+}
+
+/// Removes all but the newest `keep` snapshots, then any stored file no snapshot uses anymore.
+/// Only call it with the store locked.
+fn prune(game: &str, keep: usize) -> io::Result<()> {
+    let store = get_saves_dir()?;
+    let snapshots = list(game)?;
+
+    if snapshots.len() > keep.max(1) {
+        for old in &snapshots[..snapshots.len() - keep.max(1)] {
+            fs::remove_file(store.join(game).join(format!("{}.json", old.id)))?;
+        }
+    }
+
+    let mut used = HashSet::new();
+
+    for entry in fs::read_dir(&store)?.flatten() {
+        let path = entry.path();
+
+        if !path.is_dir() || path.file_name().is_some_and(|name| name == "objects") {
+            continue;
+        }
+
+        let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+        for snapshot in list(&name)? {
+            used.extend(snapshot.files.into_values().map(|entry| entry.hash));
+        }
+    }
+
+    let objects = store.join("objects");
+    if !objects.exists() {
+        return Ok(());
+    }
+
+    for prefix in fs::read_dir(&objects)?.flatten() {
+        for object in fs::read_dir(prefix.path())?.flatten() {
+            let name = object.file_name().to_string_lossy().to_string();
+
+            if !used.contains(&name) {
+                fs::remove_file(object.path())?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Puts the save folder back the way it was in snapshot `id`, files that weren't in the snapshot
+/// included. The current saves are snapshotted first, so a restore can always be undone.
+pub(crate) fn restore(game: &str, id: &str) -> io::Result<()> {
+    if !is_id(id) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("'{id}' isn't a snapshot id")));
+    }
+
+    let _store = lock_store();
+    let config = require_config(game)?;
+    let target = expand_home(&config.path);
+    let store = get_saves_dir()?;
+
+    let snapshot = read_snapshot(&store.join(game).join(format!("{id}.json")))
+        .map_err(|e| io::Error::new(io::ErrorKind::NotFound, format!("Snapshot '{id}' of '{game}' couldn't be read: {e}")))?;
+
+    // Paths are joined onto the save folder, so one with `..` in it could write anywhere.
+    if let Some(relative) = snapshot.files.keys().find(|relative| !relative.split('/').all(files::is_plain_name)) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Snapshot '{id}' has a file outside the save folder: {relative}")));
+    }
+
+    for entry in snapshot.files.values() {
+        if !object_path(&store, &entry.hash).exists() {
+            return Err(io::Error::other(format!("Snapshot '{id}' is missing stored file {}", entry.hash)));
+        }
+    }
+
+    if target.is_dir() {
+        take_snapshot(game, "before_restore")?;
+
+        let mut current = Vec::new();
+        walk(&target, &target, &mut current)?;
+
+        for (relative, path) in current {
+            if !snapshot.files.contains_key(&relative) {
+                fs::remove_file(path)?;
+            }
+        }
+    }
+
+    for (relative, entry) in &snapshot.files {
+        let path = target.join(relative);
+
+        if path.is_file() && hash_file(&path).is_ok_and(|(hash, _)| hash == entry.hash) {
+            continue;
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        fs::copy(object_path(&store, &entry.hash), &path)?;
+    }
+
+    output::add_log(format!("[Saves] Restored '{game}' to snapshot {id}"), output::LogLevel::Info, false);
+
+    Ok(())
+}
+
+/// Snapshot taken around a launch. Games without a save folder, or with `auto` off, are skipped.
+pub(crate) async fn auto_backup(game: &str, reason: &str) {
+    match get_config(game) {
+        Ok(Some(config)) if config.auto => {
+            let (owned_game, owned_reason) = (game.to_owned(), reason.to_owned());
+            let result = tokio::task::spawn_blocking(move || backup(&owned_game, &owned_reason))
+                .await
+                .unwrap_or_else(|e| Err(io::Error::other(e)));
+
+            if let Err(e) = result {
+                output::add_log(format!("[Saves] Failed to back up saves of '{game}' ({reason}): {e}"), output::LogLevel::Error, false);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => output::add_log(format!("[Saves] Failed to load settings: {e}"), output::LogLevel::Error, false),
+    }
+}
+
+/// The game given, or else the one the script or hook runs for.
+fn current_game(lua: &Lua, game: Option<String>) -> LuaResult<String> {
+    let game = match game {
+        Some(game) => game,
+        None => lua_utils::current_game(lua)?
+            .ok_or_else(|| LuaError::external("No game given and this script isn't running for one"))?,
+    };
+
+    if !files::is_plain_name(&game) {
+        return Err(LuaError::external(format!("'{game}' isn't a game name")));
+    }
+
+    Ok(game)
+}
+
+/// Builds the `saves` table with `backup`, `restore` and `list`.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    let module = lua.create_table()?;
+
+    module.set("backup", lua.create_async_function(|lua, game: Option<String>| async move {
+        let snapshot = os(&lua).backup_saves(&current_game(&lua, game)?).await?;
+        Ok(snapshot.id)
+    })?)?;
+
+    module.set("restore", lua.create_async_function(|lua, (id, game): (String, Option<String>)| async move {
+        os(&lua).restore_saves(&current_game(&lua, game)?, &id).await
+    })?)?;
+
+    module.set("list", lua.create_function(|lua, game: Option<String>| {
+        let snapshots: Vec<SnapshotSummary> = os(lua).list_saves(&current_game(lua, game)?)?
+            .into_iter()
+            .map(SnapshotSummary::from)
+            .collect();
+
+        lua.to_value(&snapshots)
+    })?)?;
+
+    lua.globals().set("saves", module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ids_and_paths() {
+        for id in ["001760000000000", "001760000000000-2"] {
+            assert!(is_id(id), "{id}");
+        }
+
+        for id in ["../../settings", "1760000000000", "001760000000000-", "001760000000000-../x", "00176000000000a", ""] {
+            assert!(!is_id(id), "{id}");
+        }
+
+        for name in ["saves", "slot 1.sav", ".hidden"] {
+            assert!(files::is_plain_name(name), "{name}");
+        }
+
+        for name in ["", ".", "..", "a/b", "a\\b", "/etc", "./a"] {
+            assert!(!files::is_plain_name(name), "{name}");
+        }
+    }
+}
@@ -0,0 +1,355 @@
+use chrono::{DateTime, Datelike, Local, Timelike};
+use serde::{Serialize, Deserialize};
+use std::collections::{HashMap, HashSet};
+use std::time::Duration;
+
+use crate::files::{self, expand_home};
+use crate::fs_api;
+use crate::lua_utils::{run_script, script_path};
+use crate::matching::{MatchMode, Matcher};
+use crate::output;
+use crate::process;
+use crate::runs;
+
+/// How often rules are checked. Schedules only go down to the minute, so this just has to be
+/// quick enough for app and file triggers to feel instant.
+const TICK: Duration = Duration::from_secs(2);
+
+/// What makes a rule run its script.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+#[serde(tag = "on", rename_all = "camelCase")]
+pub(crate) enum Trigger {
+    /// A cron expression like `"30 18 * * 1-5"`, in local time.
+    Schedule { cron: String },
+    /// Once when the launcher starts.
+    Startup,
+    /// When a process matching `app` starts. `*` and `?` work like in file names and case is ignored.
+    AppOpen { app: String },
+    /// When the last process matching `app` ends.
+    AppClose { app: String },
+    /// When a file, or anything directly in a folder, is added, removed or modified. A leading `~` is the home folder.
+    FileChange { path: String },
+}
+
+/// Runs a game's script when its trigger fires, stored in `Settings.rules`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct Rule {
+    pub(crate) game: String,
+    #[serde(default = "default_enabled")]
+    pub(crate) enabled: bool,
+    #[serde(flatten)]
+    pub(crate) trigger: Trigger,
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl Rule {
+    /// Catches what would otherwise only show up in the log once the scheduler gets to the rule.
+    pub(crate) fn validate(&self) -> Result<(), String> {
+        if !files::is_plain_name(&self.game) {
+            return Err(format!("'{}' isn't a valid game name", self.game));
+        }
+
+        match &self.trigger {
+            Trigger::Schedule { cron } => Cron::parse(cron).map(|_| ()),
+            Trigger::AppOpen { app } | Trigger::AppClose { app } if app.trim().is_empty() => {
+                Err("it needs an app name".to_owned())
+            }
+            Trigger::AppOpen { app } | Trigger::AppClose { app } => Matcher::new(app, MatchMode::Glob, true).map(|_| ()),
+            Trigger::FileChange { path } if path.trim().is_empty() => Err("it needs a path".to_owned()),
+            Trigger::Startup | Trigger::FileChange { .. } => Ok(()),
+        }
+        .map_err(|e| format!("The rule for {} is invalid: {e}", self.game))
+    }
+}
+
+impl Trigger {
+    fn describe(&self) -> String {
+        match self {
+            Trigger::Schedule { cron } => format!("of the schedule '{cron}'"),
+            Trigger::Startup => "the launcher started".to_owned(),
+            Trigger::AppOpen { app } => format!("{app} opened"),
+            Trigger::AppClose { app } => format!("{app} closed"),
+            Trigger::FileChange { path } => format!("{path} changed"),
+        }
+    }
+}
+
+/// One field of a cron expression, as a bit for every value it matches.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| format!("'{step}' isn't a step"))?),
+            None => (item, 1),
+        };
+
+        if step == 0 {
+            return Err(format!("'{item}' has a step of 0"));
+        }
+
+        let value = |text: &str| text.parse::<u32>().map_err(|_| format!("'{text}' isn't a number"));
+
+        let (start, end) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (value(start)?, value(end)?),
+                // `5/15` means from 5 to the end, every 15.
+                None if item.contains('/') => (value(range)?, max),
+                None => (value(range)?, value(range)?),
+            },
+        };
+
+        if start < min || end > max || start > end {
+            return Err(format!("'{item}' has to be within {min}-{max}"));
+        }
+
+        for n in (start..=end).step_by(step as usize) {
+            bits |= 1 << n;
+        }
+    }
+
+    Ok(bits)
+}
+
+/// A parsed cron expression: minute, hour, day of the month, month and day of the week.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Cron {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// Cron runs on either day field when both are restricted, rather than when both match.
+    days_restricted: bool,
+    weekdays_restricted: bool,
+}
+
+impl Cron {
+    pub(crate) fn parse(expression: &str) -> Result<Self, String> {
+        let expression = match expression.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            other => other,
+        };
+
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+
+        let [minutes, hours, days, months, weekdays] = fields.as_slice() else {
+            return Err(format!("'{expression}' needs 5 fields: minute, hour, day, month and weekday"));
+        };
+
+        let mut weekday_bits = parse_field(weekdays, 0, 7)?;
+
+        // 7 is Sunday too.
+        if weekday_bits & (1 << 7) != 0 {
+            weekday_bits |= 1;
+        }
+
+        Ok(Cron {
+            minutes: parse_field(minutes, 0, 59)?,
+            hours: parse_field(hours, 0, 23)?,
+            days: parse_field(days, 1, 31)?,
+            months: parse_field(months, 1, 12)?,
+            weekdays: weekday_bits,
+            days_restricted: *days != "*",
+            weekdays_restricted: *weekdays != "*",
+        })
+    }
+
+    pub(crate) fn matches(&self, time: &DateTime<Local>) -> bool {
+        let has = |bits: u64, n: u32| bits & (1 << n) != 0;
+
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+
+        let day_matches = match (self.days_restricted, self.weekdays_restricted) {
+            (true, true) => day || weekday,
+            _ => day && weekday,
+        };
+
+        has(self.minutes, time.minute()) && has(self.hours, time.hour()) && has(self.months, time.month()) && day_matches
+    }
+}
+
+/// What a rule saw last time, so it only fires when that changes.
+#[derive(PartialEq)]
+enum Seen {
+    Open(bool),
+    Signature(u64),
+    /// Minutes since the unix epoch, so a schedule runs once in the minute it matches.
+    Minute(i64),
+}
+
+/// Checks the rules forever. Rules are read again every tick, so changes to them apply right away.
+pub(crate) fn start() {
+    tokio::spawn(async move {
+        let mut seen: HashMap<(String, Trigger), Seen> = HashMap::new();
+        let mut reported: HashSet<String> = HashSet::new();
+        let mut first = true;
+
+        let mut interval = tokio::time::interval(TICK);
+
+        loop {
+            interval.tick().await;
+
+            let rules: Vec<Rule> = files::load_settings().ok().flatten()
+                .map(|settings| settings.rules.into_iter().filter(|rule| rule.enabled).collect())
+                .unwrap_or_default();
+
+            let processes = if rules.iter().any(|rule| matches!(rule.trigger, Trigger::AppOpen { .. } | Trigger::AppClose { .. })) {
+                process::list_processes().unwrap_or_default()
+            } else {
+                Vec::new()
+            };
+
+            let now = Local::now();
+            let mut started = HashSet::new();
+
+            // Rules that were removed or turned off start over if they come back.
+            seen.retain(|(game, trigger), _| rules.iter().any(|rule| rule.game == *game && rule.trigger == *trigger));
+
+            for rule in rules {
+                let key = (rule.game.clone(), rule.trigger.clone());
+
+                let fired = match &rule.trigger {
+                    Trigger::Startup => first,
+                    Trigger::Schedule { cron } => match Cron::parse(cron) {
+                        Ok(cron) if cron.matches(&now) => {
+                            let minute = now.timestamp() / 60;
+                            seen.insert(key, Seen::Minute(minute)) != Some(Seen::Minute(minute))
+                        }
+                        Ok(_) => false,
+                        Err(e) => {
+                            if reported.insert(cron.clone()) {
+                                output::add_log(format!("[Scheduler] The rule for {} has a bad schedule: {e}", rule.game), output::LogLevel::Error, false);
+                            }
+                            false
+                        }
+                    },
+                    Trigger::AppOpen { app } | Trigger::AppClose { app } => {
+                        let matcher = match Matcher::new(app, MatchMode::Glob, true) {
+                            Ok(matcher) => matcher,
+                            Err(e) => {
+                                if reported.insert(app.clone()) {
+                                    output::add_log(format!("[Scheduler] The rule for {} has a bad app name: {e}", rule.game), output::LogLevel::Error, false);
+                                }
+                                continue;
+                            }
+                        };
+
+                        let open = processes.iter().any(|p| matcher.is_match(&p.name));
+                        let before = seen.insert(key, Seen::Open(open));
+
+                        match rule.trigger {
+                            Trigger::AppOpen { .. } => open && before == Some(Seen::Open(false)),
+                            _ => !open && before == Some(Seen::Open(true)),
+                        }
+                    }
+                    Trigger::FileChange { path } => {
+                        let signature = fs_api::signature(&expand_home(path));
+                        let before = seen.insert(key, Seen::Signature(signature));
+
+                        before.is_some_and(|before| before != Seen::Signature(signature))
+                    }
+                };
+
+                if fired && started.insert(rule.game.clone()) {
+                    fire(rule);
+                }
+            }
+
+            first = false;
+        }
+    });
+}
+
+/// Runs the rule's script and logs how it went, which is the rule's history.
+fn fire(rule: Rule) {
+    let reason = rule.trigger.describe();
+
+    if !script_path(&rule.game).is_file() {
+        output::add_log(format!("[Scheduler] Didn't run {} because {reason}, it has no script", rule.game), output::LogLevel::Warning, false);
+        return;
+    }
+
+    if runs::list().iter().any(|run| run.game == rule.game) {
+        output::add_log(format!("[Scheduler] Didn't run {} because {reason}, it's already running", rule.game), output::LogLevel::Info, false);
+        return;
+    }
+
+    output::add_log(format!("[Scheduler] Running {} because {reason}", rule.game), output::LogLevel::Info, false);
+
+    tokio::spawn(async move {
+        let session = run_script(&rule.game).await;
+
+        let (level, ending) = match (session.outcome.as_str(), &session.error) {
+            ("success", _) => (output::LogLevel::Info, "finished".to_owned()),
+            (outcome, Some(error)) => (output::LogLevel::Error, format!("{outcome}: {error}")),
+            (outcome, None) => (output::LogLevel::Error, format!("{outcome} with exit code {}", session.exit_code.unwrap_or_default())),
+        };
+
+        output::add_log(format!("[Scheduler] {} {ending} after {:.1}s", rule.game, session.duration), level, false);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    #[test]
+    fn cron_fields() {
+        let cron = Cron::parse("*/15 9-17 * * 1-5").unwrap();
+
+        // 2026-10-19 is a Monday.
+        assert!(cron.matches(&at(2026, 10, 19, 9, 0)));
+        assert!(cron.matches(&at(2026, 10, 19, 17, 45)));
+        assert!(!cron.matches(&at(2026, 10, 19, 9, 10)));
+        assert!(!cron.matches(&at(2026, 10, 19, 18, 0)));
+        assert!(!cron.matches(&at(2026, 10, 18, 9, 0)));
+    }
+
+    #[test]
+    fn cron_days_and_aliases() {
+        // Either the 1st or a Sunday, like cron.
+        let cron = Cron::parse("0 12 1 * 7").unwrap();
+        assert!(cron.matches(&at(2026, 10, 1, 12, 0)));
+        assert!(cron.matches(&at(2026, 10, 18, 12, 0)));
+        assert!(!cron.matches(&at(2026, 10, 19, 12, 0)));
+
+        // A step is a restriction too, only a plain `*` isn't.
+        let cron = Cron::parse("0 12 */2 * 1").unwrap();
+        assert!(cron.matches(&at(2026, 10, 21, 12, 0)));
+        assert!(cron.matches(&at(2026, 10, 26, 12, 0)));
+        assert!(!cron.matches(&at(2026, 10, 20, 12, 0)));
+
+        assert_eq!(Cron::parse("@daily"), Cron::parse("0 0 * * *"));
+        assert!(Cron::parse("0 12 * *").is_err());
+        assert!(Cron::parse("60 * * * *").is_err());
+        assert!(Cron::parse("*/0 * * * *").is_err());
+    }
+
+    #[test]
+    fn rules_are_checked_before_saving() {
+        let rule = |game: &str, trigger: Trigger| Rule { game: game.to_owned(), enabled: true, trigger };
+
+        assert!(rule("Backup", Trigger::Schedule { cron: "0 18 * * 1-5".to_owned() }).validate().is_ok());
+        assert!(rule("Discord", Trigger::AppOpen { app: "steam*".to_owned() }).validate().is_ok());
+
+        let error = rule("Backup", Trigger::Schedule { cron: "0 25 * * *".to_owned() }).validate().unwrap_err();
+        assert!(error.starts_with("The rule for Backup is invalid"), "{error}");
+        assert!(rule("Sync", Trigger::AppClose { app: " ".to_owned() }).validate().is_err());
+        assert!(rule("Mods", Trigger::FileChange { path: String::new() }).validate().is_err());
+        assert!(rule("../Backup", Trigger::Startup).validate().is_err());
+    }
+}
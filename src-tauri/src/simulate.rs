@@ -0,0 +1,624 @@
+use mlua::prelude::*;
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use crate::command::{CommandOutput, CommandRequest};
+use crate::dialogs::Dialog;
+use crate::http::{HttpRequest, HttpResponse, Progress};
+use crate::notify::Notification;
+use crate::os_layer::{FileChange, OsLayer, Pending, Sleep};
+use crate::output;
+use crate::process::ProcessInfo;
+use crate::runs::Report;
+use crate::saves::Snapshot;
+use crate::window::{WindowAction, WindowInfo};
+
+/// Describes the world a simulated script runs in. Names are matched against both
+/// app and window names, and all times are in milliseconds of simulated time.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub(crate) struct Scenario {
+    /// Apps and windows that are already open when the script starts.
+    #[serde(default)]
+    pub(crate) running: Vec<String>,
+    /// When an app or window appears, counted from the start of the script.
+    #[serde(default)]
+    pub(crate) opens_after: HashMap<String, u64>,
+    /// How long an app or window stays open before the user closes it.
+    #[serde(default)]
+    pub(crate) closes_after: HashMap<String, u64>,
+    /// Apps that fail to start even when the script opens them.
+    #[serde(default)]
+    pub(crate) unavailable: Vec<String>,
+    /// Whether developer mode is on, which `runCommand` needs. Defaults to on.
+    #[serde(default)]
+    pub(crate) dev_mode: Option<bool>,
+    /// Simulated time after which the script is stopped, so waiting for something
+    /// the scenario never does can't hang. Defaults to an hour.
+    #[serde(default)]
+    pub(crate) time_limit: Option<u64>,
+    /// Capabilities the script has, like `"network"`. Defaults to all of them.
+    #[serde(default)]
+    pub(crate) capabilities: Option<Vec<String>>,
+    /// Responses for `http` calls by URL. Anything else gets a 404.
+    #[serde(default)]
+    pub(crate) http: HashMap<String, MockResponse>,
+    /// What `runCommand` gives back by command. Anything else succeeds with no output.
+    #[serde(default)]
+    pub(crate) commands: HashMap<String, MockCommand>,
+    /// Answers for `confirm`, `choose`, `prompt`, `pickFile` and `pickFolder`, used in order.
+    /// Once they run out, dialogs say yes, pick the first option or keep the default text.
+    #[serde(default)]
+    pub(crate) answers: Vec<serde_json::Value>,
+    /// What each game has in `storage` when the script starts. Changes are kept in memory.
+    #[serde(default)]
+    pub(crate) storage: HashMap<String, serde_json::Map<String, serde_json::Value>>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct MockResponse {
+    #[serde(default = "default_status")]
+    pub(crate) status: u16,
+    #[serde(default)]
+    pub(crate) body: String,
+}
+
+fn default_status() -> u16 {
+    200
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub(crate) struct MockCommand {
+    #[serde(default)]
+    pub(crate) code: i32,
+    #[serde(default)]
+    pub(crate) stdout: String,
+    #[serde(default)]
+    pub(crate) stderr: String,
+}
+
+const DEFAULT_TIME_LIMIT: u64 = 60 * 60 * 1000;
+
+#[derive(Serialize, Clone, Debug)]
+pub(crate) struct TraceEntry {
+    pub(crate) time: u64,
+    pub(crate) action: String,
+    pub(crate) detail: String,
+}
+
+#[derive(Default)]
+struct Item {
+    opened_at: Option<u64>,
+    closed_at: Option<u64>,
+}
+
+struct World {
+    clock: u64,
+    scenario: Scenario,
+    items: HashMap<String, Item>,
+    trace: Vec<TraceEntry>,
+    opened: Vec<String>,
+    closed: Vec<String>,
+    snapshots: Vec<Snapshot>,
+    storage: HashMap<String, serde_json::Map<String, serde_json::Value>>,
+    /// Sleeps that haven't finished yet, as when they're due and an id to tell them apart.
+    sleeping: Vec<(u64, u64)>,
+    /// Sleeps waiting for the ones due before them, woken when it's their turn.
+    waiting: HashMap<(u64, u64), Waker>,
+    next_sleep: u64,
+}
+
+/// Error raised by the simulated `exit` so the script stops without closing the launcher.
+#[derive(Debug)]
+pub(crate) struct ScriptExit(pub(crate) i32);
+
+impl std::fmt::Display for ScriptExit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "script exited with code {}", self.0)
+    }
+}
+
+impl std::error::Error for ScriptExit {}
+
+pub(crate) fn exit_code(err: &mlua::Error) -> Option<i32> {
+    match err {
+        mlua::Error::CallbackError { cause, .. } => exit_code(cause),
+        mlua::Error::ExternalError(e) => e.downcast_ref::<ScriptExit>().map(|exit| exit.0),
+        mlua::Error::WithContext { cause, .. } => exit_code(cause),
+        _ => None,
+    }
+}
+
+fn base_name(name: &str) -> String {
+    name.rsplit(['/', '\\']).next().unwrap_or(name).to_owned()
+}
+
+/// Whether `target`, as passed to `openApp` or `openURL`, refers to `name`. A path matches its
+/// file name with or without extension, and a URL matches its scheme (`steam://...` is `steam`).
+pub(crate) fn matches_target(target: &str, name: &str) -> bool {
+    let base = base_name(target);
+    let stem = base.rsplit_once('.').map(|(stem, _)| stem).unwrap_or(&base);
+
+    target == name
+        || base == name
+        || stem == name
+        || target.strip_prefix(name).is_some_and(|rest| rest.starts_with("://") || rest.starts_with(':'))
+}
+
+impl World {
+    fn new(scenario: Scenario) -> Self {
+        let mut items: HashMap<String, Item> = HashMap::new();
+
+        for name in &scenario.running {
+            items.insert(name.clone(), Item { opened_at: Some(0), closed_at: None });
+        }
+
+        for (name, at) in &scenario.opens_after {
+            items.entry(name.clone()).or_default().opened_at = Some(*at);
+        }
+
+        let storage = scenario.storage.clone();
+
+        let mut state = World {
+            clock: 0,
+            scenario,
+            items,
+            trace: Vec::new(),
+            opened: Vec::new(),
+            closed: Vec::new(),
+            snapshots: Vec::new(),
+            storage,
+            sleeping: Vec::new(),
+            waiting: HashMap::new(),
+            next_sleep: 0,
+        };
+
+        let names: Vec<String> = state.items.keys().cloned().collect();
+        for name in names {
+            state.schedule_close(&name);
+        }
+
+        state
+    }
+
+    fn record(&mut self, action: &str, detail: impl Into<String>) {
+        self.trace.push(TraceEntry { time: self.clock, action: action.to_owned(), detail: detail.into() });
+    }
+
+    fn schedule_close(&mut self, name: &str) {
+        let after = self.scenario.closes_after.get(name).copied();
+
+        if let (Some(item), Some(after)) = (self.items.get_mut(name), after) {
+            item.closed_at = item.opened_at.map(|at| at + after);
+        }
+    }
+
+    fn is_open(&self, name: &str) -> bool {
+        self.items.get(name).is_some_and(|item| {
+            item.opened_at.is_some_and(|at| at <= self.clock) && item.closed_at.is_none_or(|at| at > self.clock)
+        })
+    }
+
+    /// Everything open right now with a stable made-up id, sorted by name.
+    fn open_names(&self) -> Vec<(u64, String)> {
+        let mut names: Vec<&String> = self.items.keys().filter(|name| self.is_open(name)).collect();
+        names.sort();
+
+        names.into_iter()
+            .map(|name| {
+                let mut hasher = DefaultHasher::new();
+                name.hash(&mut hasher);
+                (hasher.finish(), name.clone())
+            })
+            .collect()
+    }
+
+    fn open(&mut self, name: &str) {
+        self.opened.push(name.to_owned());
+
+        for key in [name.to_owned(), base_name(name)] {
+            if self.scenario.unavailable.contains(&key) {
+                return;
+            }
+        }
+
+        for key in [name.to_owned(), base_name(name)] {
+            if self.is_open(&key) {
+                continue;
+            }
+
+            let clock = self.clock;
+            let item = self.items.entry(key.clone()).or_default();
+            item.opened_at = Some(item.opened_at.filter(|at| *at > clock).unwrap_or(clock));
+            item.closed_at = None;
+            self.schedule_close(&key);
+        }
+    }
+
+    fn close(&mut self, name: &str) {
+        self.closed.push(name.to_owned());
+        let clock = self.clock;
+
+        for key in [name.to_owned(), base_name(name)] {
+            if let Some(item) = self.items.get_mut(&key) {
+                if item.opened_at.is_some_and(|at| at <= clock) {
+                    item.closed_at = Some(clock);
+                }
+            }
+        }
+    }
+
+    /// Takes a finished sleep out of the list and wakes whichever is due next.
+    fn end_sleep(&mut self, due: (u64, u64)) {
+        self.sleeping.retain(|sleeping| *sleeping != due);
+        self.waiting.remove(&due);
+
+        if let Some(waker) = self.sleeping.iter().min().and_then(|next| self.waiting.remove(next)) {
+            waker.wake();
+        }
+    }
+}
+
+/// A simulated `sleep`. Sleeps finish in the order they're due, each moving the clock to its
+/// time, so tasks sleeping side by side see the same clock they would if they really waited.
+struct MockSleep {
+    world: Arc<Mutex<World>>,
+    due: (u64, u64),
+    /// Every sleep lets other tasks run once first, since no time would pass while they did.
+    yielded: bool,
+}
+
+impl Future for MockSleep {
+    type Output = LuaResult<()>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        if !self.yielded {
+            self.yielded = true;
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+
+        let mut world = self.world.lock().unwrap();
+        let next = world.sleeping.iter().min().copied();
+
+        // Something else is due first, so let it run. A sleep missing from the list was
+        // thrown away by `reset` and just finishes.
+        if next.is_some_and(|next| next != self.due) && world.sleeping.contains(&self.due) {
+            world.waiting.insert(self.due, cx.waker().clone());
+            drop(world);
+
+            // Once woken it yields again, so the task that just woke up can sleep before it.
+            self.yielded = false;
+            return Poll::Pending;
+        }
+
+        let due = self.due;
+        world.end_sleep(due);
+        world.clock = world.clock.max(due.0);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for MockSleep {
+    fn drop(&mut self) {
+        self.world.lock().unwrap().end_sleep(self.due);
+    }
+}
+
+/// A fake OS driven by a `Scenario` with its own clock. Side effects are recorded into a
+/// trace instead of happening, and `sleep` moves the clock forward rather than waiting.
+pub(crate) struct MockOs {
+    world: Arc<Mutex<World>>,
+}
+
+impl MockOs {
+    pub(crate) fn new(scenario: Scenario) -> Self {
+        MockOs { world: Arc::new(Mutex::new(World::new(scenario))) }
+    }
+
+    /// Throws away everything that happened and starts over from `scenario`.
+    pub(crate) fn reset(&self, scenario: Scenario) {
+        let mut world = self.world.lock().unwrap();
+        let mut fresh = World::new(scenario);
+
+        // Ids keep counting up, so a sleep from before can't be mistaken for a new one.
+        fresh.next_sleep = world.next_sleep;
+        let old = std::mem::replace(&mut *world, fresh);
+
+        // Sleeps left waiting would never be woken by the new world.
+        for waker in old.waiting.into_values() {
+            waker.wake();
+        }
+    }
+
+    pub(crate) fn trace(&self) -> Vec<TraceEntry> {
+        self.world.lock().unwrap().trace.clone()
+    }
+
+    pub(crate) fn is_open(&self, name: &str) -> bool {
+        self.world.lock().unwrap().is_open(name)
+    }
+
+    pub(crate) fn was_opened(&self, name: &str) -> bool {
+        self.world.lock().unwrap().opened.iter().any(|target| matches_target(target, name))
+    }
+
+    pub(crate) fn was_closed(&self, name: &str) -> bool {
+        self.world.lock().unwrap().closed.iter().any(|target| matches_target(target, name))
+    }
+
+    pub(crate) fn finish(&self, result: Result<(), String>) -> Vec<TraceEntry> {
+        let mut world = self.world.lock().unwrap();
+
+        match result {
+            Ok(()) => world.record("finished", "script completed"),
+            Err(e) => world.record("error", e),
+        }
+
+        world.trace.clone()
+    }
+}
+
+impl OsLayer for MockOs {
+    fn open_app(&self, app: &str, args: &[String]) -> LuaResult<()> {
+        let mut world = self.world.lock().unwrap();
+
+        world.record("openApp", if args.is_empty() { app.to_owned() } else { format!("{app} {}", args.join(" ")) });
+        world.open(app);
+        Ok(())
+    }
+
+    fn close_app(&self, process_name: &str, force: bool) -> LuaResult<()> {
+        let mut world = self.world.lock().unwrap();
+
+        world.record(if force { "forceCloseApp" } else { "closeApp" }, process_name);
+        world.close(process_name);
+        Ok(())
+    }
+
+    /// Every open app or window in the scenario shows up as a process with its name.
+    fn list_processes(&self) -> LuaResult<Vec<ProcessInfo>> {
+        Ok(self.world.lock().unwrap().open_names()
+            .into_iter()
+            .map(|(id, name)| ProcessInfo { pid: id as u32, command: name.clone(), name })
+            .collect())
+    }
+
+    /// And as a window titled with its name.
+    fn list_windows(&self) -> LuaResult<Vec<WindowInfo>> {
+        Ok(self.world.lock().unwrap().open_names()
+            .into_iter()
+            .map(|(id, name)| WindowInfo { id, title: name.clone(), class: name, ..Default::default() })
+            .collect())
+    }
+
+    fn window_action(&self, window: &WindowInfo, action: WindowAction) -> LuaResult<()> {
+        let mut world = self.world.lock().unwrap();
+
+        let detail = match action {
+            WindowAction::Move(x, y) => format!("{} -> {x}, {y}", window.title),
+            WindowAction::Resize(width, height) => format!("{} -> {width}x{height}", window.title),
+            _ => window.title.clone(),
+        };
+
+        world.record(&format!("window.{}", action.name()), detail);
+
+        if action == WindowAction::Close {
+            world.close(&window.title);
+        }
+
+        Ok(())
+    }
+
+    fn open_url(&self, url: &str) -> LuaResult<()> {
+        let mut world = self.world.lock().unwrap();
+
+        world.record("openURL", url);
+        world.open(url);
+        Ok(())
+    }
+
+    /// Snapshots are pretend ones with no files, kept so `saves.list` and `saves.restore` work.
+    fn backup_saves(&self, game: &str) -> Pending<'static, Snapshot> {
+        let mut world = self.world.lock().unwrap();
+
+        world.record("saves.backup", game);
+
+        let snapshot = Snapshot {
+            id: format!("{:015}", world.clock),
+            game: game.to_owned(),
+            created: world.clock / 1000,
+            reason: "script".to_owned(),
+            size: 0,
+            files: Default::default(),
+        };
+
+        world.snapshots.push(snapshot.clone());
+        Box::pin(async move { Ok(snapshot) })
+    }
+
+    fn restore_saves(&self, game: &str, id: &str) -> Pending<'static, ()> {
+        let mut world = self.world.lock().unwrap();
+
+        world.record("saves.restore", format!("{game} {id}"));
+
+        let result = if world.snapshots.iter().any(|s| s.game == game && s.id == id) {
+            Ok(())
+        } else {
+            Err(LuaError::external(format!("Snapshot '{id}' of '{game}' couldn't be read")))
+        };
+
+        Box::pin(async move { result })
+    }
+
+    fn list_saves(&self, game: &str) -> LuaResult<Vec<Snapshot>> {
+        Ok(self.world.lock().unwrap().snapshots.iter().filter(|s| s.game == game).cloned().collect())
+    }
+
+    fn load_storage(&self, game: &str) -> LuaResult<serde_json::Map<String, serde_json::Value>> {
+        Ok(self.world.lock().unwrap().storage.get(game).cloned().unwrap_or_default())
+    }
+
+    fn change_storage(&self, game: &str, key: &str, value: Option<serde_json::Value>) -> LuaResult<()> {
+        let mut world = self.world.lock().unwrap();
+
+        match value {
+            Some(value) => {
+                world.record("storage.set", format!("{key} = {value}"));
+                world.storage.entry(game.to_owned()).or_default().insert(key.to_owned(), value);
+            }
+            None => {
+                world.record("storage.delete", key);
+                world.storage.entry(game.to_owned()).or_default().remove(key);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn run_command(&self, request: CommandRequest) -> Pending<'static, CommandOutput> {
+        let mut world = self.world.lock().unwrap();
+        let cmd = request.display();
+
+        world.record("runCommand", cmd.as_str());
+
+        let mock = world.scenario.commands.get(&cmd).cloned().unwrap_or_default();
+        let output = CommandOutput {
+            code: Some(mock.code),
+            stdout: mock.stdout.into_bytes(),
+            stderr: mock.stderr.into_bytes(),
+            timed_out: false,
+        };
+
+        Box::pin(async move { Ok(output) })
+    }
+
+    fn message_box(&self, title: &str, desc: &str) {
+        self.world.lock().unwrap().record("messageBox", format!("{title}: {desc}"));
+    }
+
+    fn dialog(&self, dialog: Dialog) -> Pending<'static, serde_json::Value> {
+        let mut world = self.world.lock().unwrap();
+
+        let answer = if world.scenario.answers.is_empty() {
+            dialog.default_answer()
+        } else {
+            world.scenario.answers.remove(0)
+        };
+
+        world.record(dialog.name(), format!("{} -> {answer}", dialog.title()));
+
+        Box::pin(async move { Ok(answer) })
+    }
+
+    fn notify(&self, notification: Notification) -> Pending<'static, bool> {
+        self.world.lock().unwrap().record("notify", format!("{}: {}", notification.title, notification.body));
+
+        Box::pin(async move { Ok(true) })
+    }
+
+    fn report(&self, game: &str, report: Report) {
+        let mut world = self.world.lock().unwrap();
+        let (action, text) = match report {
+            Report::Progress { fraction, message } => {
+                ("progress", format!("{:.0}% {}", fraction.clamp(0.0, 1.0) * 100.0, message.unwrap_or_default()).trim_end().to_owned())
+            }
+            Report::Status(text) => ("status", text.unwrap_or_default()),
+        };
+
+        if game.is_empty() {
+            world.record(action, text);
+        } else {
+            world.record(action, format!("{game}: {text}"));
+        }
+    }
+
+    fn sleep(&self, duration: Duration) -> Sleep {
+        let mut world = self.world.lock().unwrap();
+        let limit = world.scenario.time_limit.unwrap_or(DEFAULT_TIME_LIMIT);
+        let due = world.clock + duration.as_millis() as u64;
+
+        if due > limit {
+            world.clock = due;
+            return Box::pin(async move {
+                Err(LuaError::external(format!("Simulated time limit of {limit} ms reached, the script is waiting for something the scenario never does")))
+            });
+        }
+
+        let id = world.next_sleep;
+        world.next_sleep += 1;
+        world.sleeping.push((due, id));
+
+        Box::pin(MockSleep { world: self.world.clone(), due: (due, id), yielded: false })
+    }
+
+    fn exit(&self, code: i32) -> LuaResult<()> {
+        self.world.lock().unwrap().record("exit", code.to_string());
+
+        Err(LuaError::external(ScriptExit(code)))
+    }
+
+    fn dev_mode(&self) -> LuaResult<Option<bool>> {
+        Ok(Some(self.world.lock().unwrap().scenario.dev_mode.unwrap_or(true)))
+    }
+
+    fn has_capability(&self, _game: Option<&str>, name: &str) -> LuaResult<bool> {
+        Ok(self.world.lock().unwrap().scenario.capabilities.as_ref().is_none_or(|caps| caps.iter().any(|cap| cap == name)))
+    }
+
+    fn http(&self, request: HttpRequest) -> Pending<'static, HttpResponse> {
+        let mut world = self.world.lock().unwrap();
+
+        world.record("http", format!("{} {}", request.method.to_uppercase(), request.url));
+
+        let response = match world.scenario.http.get(&request.url) {
+            Some(mock) => HttpResponse { status: mock.status, headers: HashMap::new(), body: mock.body.clone().into_bytes() },
+            None => HttpResponse { status: 404, ..Default::default() },
+        };
+
+        Box::pin(async move { Ok(response) })
+    }
+
+    /// Nothing is written, but `progress` still sees the whole body arrive.
+    fn download<'a>(&self, request: HttpRequest, path: PathBuf, mut progress: Progress<'a>) -> Pending<'a, u64> {
+        let mut world = self.world.lock().unwrap();
+
+        world.record("http.download", format!("{} -> {}", request.url, path.display()));
+
+        let result = match world.scenario.http.get(&request.url) {
+            Some(mock) if (200..300).contains(&mock.status) => {
+                let size = mock.body.len() as u64;
+                progress(0, Some(size)).and_then(|_| progress(size, Some(size))).map(|_| size)
+            }
+            _ => Err(LuaError::external(format!("Download of {} failed: the scenario has no response for it", request.url))),
+        };
+
+        Box::pin(async move { result })
+    }
+
+    fn log(&self, message: &str, level: output::LogLevel, _dev_mode: bool) {
+        let level = match level {
+            output::LogLevel::Info => "Info",
+            output::LogLevel::Warning => "Warning",
+            output::LogLevel::Error => "Error",
+        };
+
+        self.world.lock().unwrap().record("log", format!("[{level}] {message}"));
+    }
+
+    fn modify_files(&self, action: &str, detail: &str, _change: FileChange) -> LuaResult<()> {
+        self.world.lock().unwrap().record(action, detail);
+        Ok(())
+    }
+
+    fn note(&self, action: &str, detail: &str) {
+        self.world.lock().unwrap().record(action, detail);
+    }
+}
@@ -0,0 +1,621 @@
+use serde::{Serialize, Deserialize};
+use std::collections::HashSet;
+
+#[cfg(target_os = "linux")]
+use directories::BaseDirs;
+#[cfg(target_os = "linux")]
+use std::{fs, path::{Path, PathBuf}};
+#[cfg(target_os = "linux")]
+use serde_json::Value;
+
+use crate::files;
+#[cfg(target_os = "linux")]
+use crate::lua_utils::lua_quote;
+use crate::output;
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct CandidateGame {
+    pub(crate) source: String,
+    pub(crate) name: String,
+    pub(crate) command: String,
+    pub(crate) icon: Option<String>,
+    pub(crate) script: String,
+}
+
+/// A launcher or directory Luauncher can pull already installed games from.
+pub(crate) trait LibrarySource {
+    fn name(&self) -> &'static str;
+    fn scan(&self) -> Result<Vec<CandidateGame>, String>;
+}
+
+fn sources() -> Vec<Box<dyn LibrarySource>> {
+    #[cfg(target_os = "linux")]
+    {
+        vec![
+            Box::new(Lutris),
+            Box::new(Heroic),
+            Box::new(Itch),
+            Box::new(DesktopEntries),
+        ]
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    {
+        Vec::new()
+    }
+}
+
+/// Runs every source and returns the games that aren't already in the library.
+/// Sources are scanned in order, so a game found by Lutris wins over the `.desktop` file Lutris made for it.
+pub(crate) fn scan_all() -> Vec<CandidateGame> {
+    let mut seen: HashSet<String> = match files::load_settings() {
+        Ok(Some(settings)) => settings.games.keys().map(|k| k.trim().to_lowercase()).collect(),
+        _ => HashSet::new(),
+    };
+
+    let mut found = Vec::new();
+
+    for source in sources() {
+        match source.scan() {
+            Ok(games) => {
+                for game in games {
+                    if seen.insert(game.name.trim().to_lowercase()) {
+                        found.push(game);
+                    }
+                }
+            }
+            Err(e) => {
+                output::add_log(format!("[Scanning {}] {e}", source.name()), output::LogLevel::Warning, true);
+            }
+        }
+    }
+
+    found.sort_by_key(|game| game.name.to_lowercase());
+    found
+}
+
+/// Turns a store's title into a name that works as a file name on every OS, since it names the
+/// game's script and icon. `None` when nothing is left, like for a title of only dots.
+fn file_name(title: &str) -> Option<String> {
+    let cleaned: String = title.chars()
+        .map(|c| if c.is_control() || r#"/\:*?"<>|"#.contains(c) { ' ' } else { c })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+    // Windows drops trailing dots, and a name of only dots would be `.` or `..`.
+    let cleaned = cleaned.trim_end_matches(|c: char| c == '.' || c.is_whitespace()).trim();
+
+    if cleaned.is_empty() {
+        None
+    } else {
+        Some(cleaned.to_owned())
+    }
+}
+
+/// Adds the games to `Settings.games`, writes their generated scripts and copies any PNG icon into the icon directory.
+/// A game that can't be imported is logged and skipped, the rest are still imported.
+pub(crate) fn import(games: &[CandidateGame]) -> Result<usize, String> {
+    let mut settings = files::load_settings()
+        .map_err(|e| e.to_string())?
+        .ok_or("Settings not found")?;
+
+    let mut imported = 0;
+
+    for game in games {
+        let Some(name) = file_name(&game.name) else {
+            output::add_log(format!("[Importing Game] Skipped '{}', its name can't be used as a file name", game.name), output::LogLevel::Warning, false);
+            continue;
+        };
+
+        if settings.games.keys().any(|k| k.trim().eq_ignore_ascii_case(&name)) {
+            continue;
+        }
+
+        if let Err(e) = files::save_script(&name, &game.script) {
+            output::add_log(format!("[Importing Game] Failed to write the script for '{name}': {e}"), output::LogLevel::Error, false);
+            continue;
+        }
+
+        settings.games.insert(name.clone(), game.command.clone());
+
+        if let (Some(icon), Some(icon_dir)) = (&game.icon, files::get_icon_dir()) {
+            if icon.to_lowercase().ends_with(".png") {
+                if let Err(e) = std::fs::copy(icon, icon_dir.join(format!("{}.png", name))) {
+                    output::add_log(format!("[Importing Game] Failed to copy icon for '{name}': {e}"), output::LogLevel::Warning, true);
+                }
+            }
+        }
+
+        imported += 1;
+    }
+
+    files::save_settings(&settings).map_err(|e| e.to_string())?;
+
+    output::add_log(format!("[Importing Games] Imported {imported} game(s)."), output::LogLevel::Info, false);
+
+    Ok(imported)
+}
+
+#[cfg(target_os = "linux")]
+fn home() -> Result<PathBuf, String> {
+    BaseDirs::new()
+        .map(|dirs| dirs.home_dir().to_path_buf())
+        .ok_or("Failed to get home directory".to_owned())
+}
+
+#[cfg(target_os = "linux")]
+fn existing(paths: Vec<PathBuf>) -> Vec<PathBuf> {
+    paths.into_iter().filter(|p| p.exists()).collect()
+}
+
+#[cfg(target_os = "linux")]
+fn read_json(path: &Path) -> Option<Value> {
+    let data = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data).ok()
+}
+
+#[cfg(target_os = "linux")]
+fn first_existing(paths: impl IntoIterator<Item = PathBuf>) -> Option<String> {
+    paths.into_iter()
+        .find(|p| p.is_file())
+        .map(|p| p.to_string_lossy().to_string())
+}
+
+/// The first line of a generated script. Line breaks in the title would end the comment, so they're spaces.
+#[cfg(target_os = "linux")]
+fn comment(title: &str) -> String {
+    let title: String = title.chars().map(|c| if c.is_control() { ' ' } else { c }).collect();
+    format!("-- {title}\n")
+}
+
+/// Builds a script that launches `program` with `args` through `openApp`.
+#[cfg(target_os = "linux")]
+fn open_app_script(name: &str, program: &str, args: &[String]) -> String {
+    if args.is_empty() {
+        format!("{}openApp({})\n", comment(name), lua_quote(program))
+    } else {
+        let quoted: Vec<String> = args.iter().map(|a| lua_quote(a)).collect();
+        format!("{}openApp({}, {{{}}})\n", comment(name), lua_quote(program), quoted.join(", "))
+    }
+}
+
+/// Lutris keeps one YAML file per game named `<slug>-<timestamp>.yml`.
+/// The display name lives in its SQLite database, so it's rebuilt from the slug instead.
+#[cfg(target_os = "linux")]
+struct Lutris;
+
+#[cfg(target_os = "linux")]
+impl LibrarySource for Lutris {
+    fn name(&self) -> &'static str {
+        "Lutris"
+    }
+
+    fn scan(&self) -> Result<Vec<CandidateGame>, String> {
+        let home = home()?;
+        let data = home.join(".local/share/lutris");
+        let dirs = existing(vec![
+            data.join("games"),
+            home.join(".config/lutris/games"),
+        ]);
+
+        let mut games = Vec::new();
+
+        for dir in dirs {
+            let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read {}: {e}", dir.display()))?;
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+
+                if path.extension().and_then(|e| e.to_str()) != Some("yml") {
+                    continue;
+                }
+
+                let Some(stem) = path.file_stem().and_then(|s| s.to_str()) else { continue };
+                let slug = match stem.rsplit_once('-') {
+                    Some((slug, stamp)) if stamp.chars().all(|c| c.is_ascii_digit()) => slug,
+                    _ => stem,
+                };
+
+                let name = slug.split('-')
+                    .filter(|w| !w.is_empty())
+                    .map(|w| {
+                        let mut chars = w.chars();
+                        match chars.next() {
+                            Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+                            None => String::new(),
+                        }
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ");
+
+                let url = format!("lutris:rungame/{slug}");
+
+                games.push(CandidateGame {
+                    source: self.name().to_owned(),
+                    script: open_app_script(&name, "lutris", std::slice::from_ref(&url)),
+                    command: format!("lutris {url}"),
+                    icon: first_existing([
+                        home.join(format!(".local/share/icons/hicolor/128x128/apps/lutris_{slug}.png")),
+                        data.join(format!("icons/{slug}.png")),
+                        data.join(format!("coverart/{slug}.jpg")),
+                    ]),
+                    name,
+                });
+            }
+        }
+
+        Ok(games)
+    }
+}
+
+/// Heroic stores Epic (legendary), GOG and sideloaded games in separate JSON files.
+#[cfg(target_os = "linux")]
+struct Heroic;
+
+#[cfg(target_os = "linux")]
+impl Heroic {
+    fn candidate(&self, config: &Path, runner: &str, app_name: &str, title: &str) -> CandidateGame {
+        let url = format!("heroic://launch/{runner}/{app_name}");
+
+        CandidateGame {
+            source: self.name().to_owned(),
+            name: title.to_owned(),
+            command: format!("xdg-open {url}"),
+            icon: first_existing([
+                config.join(format!("icons/{app_name}.png")),
+                config.join(format!("icons/{app_name}.jpg")),
+            ]),
+            script: format!("{}openURL({})\n", comment(title), lua_quote(&url)),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl LibrarySource for Heroic {
+    fn name(&self) -> &'static str {
+        "Heroic"
+    }
+
+    fn scan(&self) -> Result<Vec<CandidateGame>, String> {
+        let home = home()?;
+        let configs = existing(vec![
+            home.join(".config/heroic"),
+            home.join(".var/app/com.heroicgameslauncher.hgl/config/heroic"),
+        ]);
+
+        let mut games = Vec::new();
+
+        for config in configs {
+            if let Some(Value::Object(installed)) = read_json(&config.join("legendaryConfig/legendary/installed.json")) {
+                for (app_name, game) in installed {
+                    let title = game["title"].as_str().unwrap_or(&app_name);
+                    games.push(self.candidate(&config, "legendary", &app_name, title));
+                }
+            }
+
+            if let Some(installed) = read_json(&config.join("gog_store/installed.json")) {
+                let library = read_json(&config.join("store_cache/gog_library.json"))
+                    .or_else(|| read_json(&config.join("gog_store/library.json")));
+
+                for game in installed["installed"].as_array().into_iter().flatten() {
+                    let Some(app_name) = game["appName"].as_str() else { continue };
+
+                    let title = library.as_ref()
+                        .and_then(|lib| lib["games"].as_array())
+                        .and_then(|list| list.iter().find(|g| g["app_name"].as_str() == Some(app_name)))
+                        .and_then(|g| g["title"].as_str().map(str::to_owned))
+                        .or_else(|| game["install_path"].as_str()
+                            .and_then(|p| Path::new(p).file_name())
+                            .map(|n| n.to_string_lossy().to_string()))
+                        .unwrap_or_else(|| app_name.to_owned());
+
+                    games.push(self.candidate(&config, "gog", app_name, &title));
+                }
+            }
+
+            if let Some(sideloaded) = read_json(&config.join("sideload_apps/library.json")) {
+                for game in sideloaded["games"].as_array().into_iter().flatten() {
+                    let Some(app_name) = game["app_name"].as_str() else { continue };
+                    let title = game["title"].as_str().unwrap_or(app_name);
+
+                    games.push(self.candidate(&config, "sideload", app_name, title));
+                }
+            }
+        }
+
+        Ok(games)
+    }
+}
+
+/// The itch app writes a gzipped receipt into every install folder listing the game and its files.
+#[cfg(target_os = "linux")]
+struct Itch;
+
+#[cfg(target_os = "linux")]
+impl Itch {
+    fn find_executable(install: &Path, receipt: &Value) -> Option<PathBuf> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut executables: Vec<PathBuf> = receipt["files"].as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|f| f.as_str())
+            .map(|f| install.join(f))
+            .filter(|p| {
+                let ext = p.extension().and_then(|e| e.to_str()).unwrap_or("");
+                !matches!(ext, "so" | "txt" | "md" | "dll" | "exe")
+                    && fs::metadata(p).map(|m| m.is_file() && m.permissions().mode() & 0o111 != 0).unwrap_or(false)
+            })
+            .collect();
+
+        executables.sort_by_key(|p| {
+            let ext = p.extension().and_then(|e| e.to_str()).unwrap_or("");
+            let preferred = matches!(ext, "x86_64" | "sh" | "AppImage");
+            (!preferred, p.components().count())
+        });
+
+        executables.into_iter().next()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl LibrarySource for Itch {
+    fn name(&self) -> &'static str {
+        "itch"
+    }
+
+    fn scan(&self) -> Result<Vec<CandidateGame>, String> {
+        use flate2::read::GzDecoder;
+        use std::io::Read;
+
+        let apps = home()?.join(".config/itch/apps");
+
+        if !apps.exists() {
+            return Ok(Vec::new());
+        }
+
+        let entries = fs::read_dir(&apps).map_err(|e| format!("Failed to read {}: {e}", apps.display()))?;
+        let mut games = Vec::new();
+
+        for entry in entries.flatten() {
+            let install = entry.path();
+            let Ok(file) = fs::File::open(install.join(".itch/receipt.json.gz")) else { continue };
+
+            let mut data = String::new();
+            if GzDecoder::new(file).read_to_string(&mut data).is_err() {
+                continue;
+            }
+
+            let Ok(receipt) = serde_json::from_str::<Value>(&data) else { continue };
+            let Some(exe) = Self::find_executable(&install, &receipt) else { continue };
+
+            let name = receipt["game"]["title"].as_str()
+                .map(str::to_owned)
+                .unwrap_or_else(|| entry.file_name().to_string_lossy().to_string());
+            let exe = exe.to_string_lossy().to_string();
+
+            games.push(CandidateGame {
+                source: self.name().to_owned(),
+                script: open_app_script(&name, &exe, &[]),
+                command: exe,
+                icon: None,
+                name,
+            });
+        }
+
+        Ok(games)
+    }
+}
+
+/// Plain freedesktop `.desktop` files in the Game category.
+#[cfg(target_os = "linux")]
+struct DesktopEntries;
+
+#[cfg(target_os = "linux")]
+impl DesktopEntries {
+    fn parse(data: &str) -> Option<(String, String, Option<String>)> {
+        let mut in_entry = false;
+        let (mut name, mut exec, mut icon) = (None, None, None);
+        let mut is_game = false;
+
+        for line in data.lines() {
+            let line = line.trim();
+
+            if line.starts_with('[') {
+                in_entry = line == "[Desktop Entry]";
+                continue;
+            }
+
+            if !in_entry || line.starts_with('#') {
+                continue;
+            }
+
+            let Some((key, value)) = line.split_once('=') else { continue };
+            let value = value.trim();
+
+            match key.trim() {
+                "Name" => name = Some(value.to_owned()),
+                "Exec" => exec = Some(value.to_owned()),
+                "Icon" => icon = Some(value.to_owned()),
+                "Type" if value != "Application" => return None,
+                "NoDisplay" | "Hidden" if value == "true" => return None,
+                "Categories" => is_game = value.split(';').any(|c| c == "Game"),
+                _ => {}
+            }
+        }
+
+        if !is_game {
+            return None;
+        }
+
+        Some((name?, exec?, icon))
+    }
+
+    /// Splits an `Exec` value into arguments, honouring quotes and dropping field codes like `%U`.
+    fn split_exec(exec: &str) -> Vec<String> {
+        let mut args = Vec::new();
+        let mut current = String::new();
+        let mut quoted = false;
+        let mut has_arg = false;
+        let mut chars = exec.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '"' => {
+                    quoted = !quoted;
+                    has_arg = true;
+                }
+                '\\' if quoted => {
+                    if let Some(next) = chars.next() {
+                        current.push(next);
+                    }
+                }
+                '%' => {
+                    if chars.next() == Some('%') {
+                        current.push('%');
+                    }
+                }
+                c if c.is_whitespace() && !quoted => {
+                    if has_arg || !current.is_empty() {
+                        args.push(std::mem::take(&mut current));
+                    }
+                    has_arg = false;
+                }
+                c => current.push(c),
+            }
+        }
+
+        if has_arg || !current.is_empty() {
+            args.push(current);
+        }
+
+        args.retain(|a| !a.is_empty());
+        args
+    }
+
+    fn resolve_icon(home: &Path, icon: &str) -> Option<String> {
+        if icon.starts_with('/') {
+            return first_existing([PathBuf::from(icon)]);
+        }
+
+        let mut candidates = Vec::new();
+
+        for base in [home.join(".local/share/icons/hicolor"), PathBuf::from("/usr/share/icons/hicolor")] {
+            for size in ["512x512", "256x256", "128x128", "96x96", "64x64", "48x48"] {
+                candidates.push(base.join(format!("{size}/apps/{icon}.png")));
+            }
+        }
+
+        candidates.push(PathBuf::from(format!("/usr/share/pixmaps/{icon}.png")));
+
+        first_existing(candidates)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl LibrarySource for DesktopEntries {
+    fn name(&self) -> &'static str {
+        "Desktop Entries"
+    }
+
+    fn scan(&self) -> Result<Vec<CandidateGame>, String> {
+        let home = home()?;
+        let applications = home.join(".local/share/applications");
+
+        if !applications.exists() {
+            return Ok(Vec::new());
+        }
+
+        let mut dirs = vec![applications];
+        let mut games = Vec::new();
+
+        while let Some(dir) = dirs.pop() {
+            let entries = fs::read_dir(&dir).map_err(|e| format!("Failed to read {}: {e}", dir.display()))?;
+
+            for entry in entries.flatten() {
+                let path = entry.path();
+
+                if path.is_dir() {
+                    dirs.push(path);
+                    continue;
+                }
+
+                if path.extension().and_then(|e| e.to_str()) != Some("desktop") {
+                    continue;
+                }
+
+                let Ok(data) = fs::read_to_string(&path) else { continue };
+                let Some((name, exec, icon)) = Self::parse(&data) else { continue };
+
+                let mut args = Self::split_exec(&exec);
+                if args.is_empty() {
+                    continue;
+                }
+                let program = args.remove(0);
+
+                games.push(CandidateGame {
+                    source: self.name().to_owned(),
+                    script: open_app_script(&name, &program, &args),
+                    command: exec,
+                    icon: icon.and_then(|i| Self::resolve_icon(&home, &i)),
+                    name,
+                });
+            }
+        }
+
+        Ok(games)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn titles_become_plain_file_names() {
+        assert_eq!(file_name("Half-Life 2").as_deref(), Some("Half-Life 2"));
+        assert_eq!(file_name("Tom Clancy's: Rainbow/Six").as_deref(), Some("Tom Clancy's Rainbow Six"));
+        assert_eq!(file_name("../../settings").as_deref(), Some(".. .. settings"));
+        assert_eq!(file_name("..").as_deref(), None);
+        assert_eq!(file_name(". .").as_deref(), None);
+        assert_eq!(file_name("  \t ").as_deref(), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn desktop_entries() {
+        let game = "[Desktop Entry]\nType=Application\nName=Doom\nExec=gzdoom %U\nIcon=doom\nCategories=Game;ActionGame;\n";
+        assert_eq!(DesktopEntries::parse(game), Some(("Doom".to_owned(), "gzdoom %U".to_owned(), Some("doom".to_owned()))));
+
+        // Only the [Desktop Entry] group counts.
+        let actions = "[Desktop Entry]\nName=Doom\nExec=gzdoom\nCategories=Game;\n[Desktop Action New]\nName=New Game\nExec=gzdoom -new\n";
+        assert_eq!(DesktopEntries::parse(actions), Some(("Doom".to_owned(), "gzdoom".to_owned(), None)));
+
+        for skipped in [
+            "[Desktop Entry]\nName=Editor\nExec=editor\nCategories=Utility;\n",
+            "[Desktop Entry]\nName=Doom\nExec=gzdoom\nCategories=Game;\nNoDisplay=true\n",
+            "[Desktop Entry]\nType=Link\nName=Doom\nExec=gzdoom\nCategories=Game;\n",
+            "[Desktop Entry]\nName=Doom\nCategories=Game;\n",
+            "Name=Doom\nExec=gzdoom\nCategories=Game;\n",
+        ] {
+            assert_eq!(DesktopEntries::parse(skipped), None, "{skipped}");
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn exec_lines() {
+        let cases: &[(&str, &[&str])] = &[
+            ("gzdoom %U", &["gzdoom"]),
+            ("env WINEPREFIX=/games/wine wine game.exe", &["env", "WINEPREFIX=/games/wine", "wine", "game.exe"]),
+            (r#""/opt/My Game/run" --fullscreen"#, &["/opt/My Game/run", "--fullscreen"]),
+            (r#"sh -c "echo \"hi\"""#, &["sh", "-c", r#"echo "hi""#]),
+            ("game --progress=100%%", &["game", "--progress=100%"]),
+            ("  spaced   out  ", &["spaced", "out"]),
+            ("", &[]),
+        ];
+
+        for (exec, args) in cases {
+            assert_eq!(DesktopEntries::split_exec(exec), *args, "{exec}");
+        }
+    }
+}
@@ -0,0 +1,107 @@
+use mlua::prelude::*;
+use serde_json::{Map, Value};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+use crate::files::{self, get_app_base};
+use crate::lua_utils;
+use crate::os_layer::os;
+
+/// Largest a script's storage file may get, so a runaway loop can't fill the disk.
+const MAX_BYTES: usize = 1024 * 1024;
+
+/// Writes are read-modify-write, so two scripts saving at once could lose a key without this.
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+fn get_storage_path(game: &str) -> io::Result<PathBuf> {
+    if !files::is_plain_name(game) {
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("'{game}' isn't a valid game name")));
+    }
+
+    get_app_base()
+        .map(|base| base.join("storage").join(format!("{game}.json")))
+        .ok_or_else(|| io::Error::other("Failed to get app base directory"))
+}
+
+pub(crate) fn load(game: &str) -> io::Result<Map<String, Value>> {
+    let path = get_storage_path(game)?;
+
+    if !path.exists() {
+        return Ok(Map::new());
+    }
+
+    serde_json::from_str(&fs::read_to_string(&path)?)
+        .map_err(|e| io::Error::other(format!("Storage file '{}' is corrupt: {e}", path.display())))
+}
+
+/// Writes to a temporary file and renames it over the old one, so a crash mid-write keeps the old data.
+fn save(game: &str, data: &Map<String, Value>) -> io::Result<()> {
+    let path = get_storage_path(game)?;
+    let json = serde_json::to_string_pretty(data).map_err(io::Error::other)?;
+
+    if json.len() > MAX_BYTES {
+        return Err(io::Error::other(format!("Storage is limited to {} KB per script", MAX_BYTES / 1024)));
+    }
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    let temp = path.with_extension("json.tmp");
+    fs::write(&temp, json)?;
+    fs::rename(&temp, &path)
+}
+
+pub(crate) fn update(game: &str, change: impl FnOnce(&mut Map<String, Value>)) -> io::Result<()> {
+    let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+
+    let mut data = load(game)?;
+    change(&mut data);
+    save(game, &data)
+}
+
+fn current_game(lua: &Lua) -> LuaResult<String> {
+    lua_utils::current_game(lua)?
+        .ok_or_else(|| LuaError::external("storage can only be used by a game's script or hooks"))
+}
+
+/// Builds the `storage` table. Values can be anything JSON can hold, so tables come back as tables.
+/// It goes through the OS layer, so a simulated script reads back what it stored without touching the real file.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    let module = lua.create_table()?;
+
+    module.set("get", lua.create_function(|lua, (key, default): (String, LuaValue)| {
+        let data = os(lua).load_storage(&current_game(lua)?)?;
+
+        match data.get(&key) {
+            Some(value) => lua.to_value(value),
+            None => Ok(default),
+        }
+    })?)?;
+
+    module.set("set", lua.create_function(|lua, (key, value): (String, LuaValue)| {
+        let json = if value.is_nil() {
+            None
+        } else {
+            Some(lua.from_value(value).map_err(|e| LuaError::external(format!("Can't store '{key}': {e}")))?)
+        };
+
+        os(lua).change_storage(&current_game(lua)?, &key, json)
+    })?)?;
+
+    module.set("delete", lua.create_function(|lua, key: String| {
+        os(lua).change_storage(&current_game(lua)?, &key, None)
+    })?)?;
+
+    module.set("keys", lua.create_function(|lua, ()| {
+        let data = os(lua).load_storage(&current_game(lua)?)?;
+        let mut keys: Vec<String> = data.into_iter().map(|(key, _)| key).collect();
+        keys.sort();
+
+        lua.to_value(&keys)
+    })?)?;
+
+    lua.globals().set("storage", module)
+}
@@ -0,0 +1,219 @@
+use chrono::{Datelike, Local, Timelike};
+use mlua::prelude::*;
+use serde::Serialize;
+use sysinfo::{CpuRefreshKind, System};
+
+use crate::os_layer::os;
+
+#[derive(Serialize)]
+struct Screen {
+    name: Option<String>,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    scale: f64,
+    primary: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Cpu {
+    name: String,
+    cores: usize,
+    physical_cores: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct Memory {
+    total: u64,
+    available: u64,
+}
+
+/// `battery` is the charge in percent, or `None` on computers without one.
+#[derive(Serialize, Default)]
+#[serde(rename_all = "camelCase")]
+struct Power {
+    #[serde(rename = "onAC")]
+    on_ac: bool,
+    battery: Option<u8>,
+    charging: bool,
+}
+
+#[derive(Serialize)]
+struct Time {
+    unix: i64,
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    second: u32,
+    /// 1 is Monday, 7 is Sunday.
+    weekday: u32,
+    iso: String,
+}
+
+/// Screens come from the launcher's own window, so there are none when it runs without one.
+fn screens() -> Vec<Screen> {
+    let Some(app) = crate::app_handle() else {
+        return Vec::new();
+    };
+
+    let primary = app.primary_monitor().ok().flatten().map(|m| *m.position());
+
+    app.available_monitors()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|monitor| Screen {
+            name: monitor.name().cloned(),
+            x: monitor.position().x,
+            y: monitor.position().y,
+            width: monitor.size().width,
+            height: monitor.size().height,
+            scale: monitor.scale_factor(),
+            primary: primary == Some(*monitor.position()),
+        })
+        .collect()
+}
+
+fn username() -> Option<String> {
+    std::env::var("USER").or_else(|_| std::env::var("USERNAME")).ok()
+}
+
+#[cfg(target_os = "linux")]
+fn power() -> Power {
+    use std::fs;
+
+    let mut power = Power::default();
+    let mut has_mains = false;
+
+    for entry in fs::read_dir("/sys/class/power_supply").into_iter().flatten().flatten() {
+        let read = |name: &str| fs::read_to_string(entry.path().join(name)).map(|s| s.trim().to_owned()).unwrap_or_default();
+
+        match read("type").as_str() {
+            "Mains" => {
+                has_mains = true;
+                power.on_ac |= read("online") == "1";
+            }
+            "Battery" if read("scope") != "Device" => {
+                power.battery = read("capacity").parse().ok().or(power.battery);
+                power.charging |= read("status") == "Charging";
+            }
+            _ => {}
+        }
+    }
+
+    // Desktops often don't report a power supply at all.
+    if !has_mains && power.battery.is_none() {
+        power.on_ac = true;
+    }
+
+    power
+}
+
+#[cfg(target_os = "windows")]
+fn power() -> Power {
+    use winapi::um::winbase::{GetSystemPowerStatus, SYSTEM_POWER_STATUS};
+
+    let mut status: SYSTEM_POWER_STATUS = unsafe { std::mem::zeroed() };
+
+    if unsafe { GetSystemPowerStatus(&mut status) } == 0 {
+        return Power { on_ac: true, ..Default::default() };
+    }
+
+    // Flag 128 is "no battery" and 255 is "unknown" for both fields.
+    let has_battery = status.BatteryFlag != 128 && status.BatteryFlag != 255;
+
+    Power {
+        on_ac: status.ACLineStatus != 0,
+        battery: (has_battery && status.BatteryLifePercent != 255).then_some(status.BatteryLifePercent),
+        charging: has_battery && status.BatteryFlag & 8 != 0,
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn power() -> Power {
+    // Looks like: Now drawing from 'Battery Power'\n -InternalBattery-0 (id=...)	87%; discharging; ...
+    let output = std::process::Command::new("pmset").args(["-g", "batt"]).output();
+    let text = output.map(|o| String::from_utf8_lossy(&o.stdout).to_string()).unwrap_or_default();
+
+    let battery = text.lines()
+        .find(|line| line.contains("InternalBattery"))
+        .and_then(|line| line.split_whitespace().find(|word| word.contains('%')))
+        .and_then(|word| word.split('%').next()?.parse().ok());
+
+    Power {
+        on_ac: !text.contains("'Battery Power'"),
+        battery,
+        charging: text.contains("; charging"),
+    }
+}
+
+fn now() -> Time {
+    let now = Local::now();
+
+    Time {
+        unix: now.timestamp(),
+        year: now.year(),
+        month: now.month(),
+        day: now.day(),
+        hour: now.hour(),
+        minute: now.minute(),
+        second: now.second(),
+        weekday: now.weekday().number_from_monday(),
+        iso: now.to_rfc3339(),
+    }
+}
+
+/// Like `lua.to_value`, but missing values are nil instead of `json.null` so `if info.battery then` works.
+fn to_lua<T: Serialize>(lua: &Lua, value: &T) -> LuaResult<LuaValue> {
+    lua.to_value_with(value, LuaSerializeOptions::new().serialize_none_to_null(false))
+}
+
+/// Builds the `sys` table. Things that can't change while a script runs are plain values,
+/// everything else is a function so it's read when the script asks.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    let module = lua.create_table()?;
+
+    module.set("os", System::name())?;
+    module.set("osVersion", System::long_os_version().or_else(System::os_version))?;
+    module.set("arch", System::cpu_arch())?;
+    module.set("hostname", System::host_name())?;
+    module.set("username", username())?;
+
+    module.set("screens", lua.create_function(|lua, ()| to_lua(lua, &screens()))?)?;
+
+    module.set("cpu", lua.create_function(|lua, ()| {
+        let mut system = System::new();
+        system.refresh_cpu_list(CpuRefreshKind::nothing());
+
+        to_lua(lua, &Cpu {
+            name: system.cpus().first().map(|cpu| cpu.brand().trim().to_owned()).unwrap_or_default(),
+            cores: system.cpus().len(),
+            physical_cores: system.physical_core_count(),
+        })
+    })?)?;
+
+    module.set("memory", lua.create_function(|lua, ()| {
+        let mut system = System::new();
+        system.refresh_memory();
+
+        lua.to_value(&Memory { total: system.total_memory(), available: system.available_memory() })
+    })?)?;
+
+    module.set("power", lua.create_function(|lua, ()| to_lua(lua, &power()))?)?;
+    module.set("time", lua.create_function(|lua, ()| lua.to_value(&now()))?)?;
+
+    // Only reads, scripts can't change the launcher's environment.
+    module.set("env", lua.create_function(|lua, name: Option<String>| match name {
+        Some(name) => std::env::var(name).ok().into_lua(lua),
+        None => lua.to_value(&std::env::vars().collect::<std::collections::HashMap<_, _>>()),
+    })?)?;
+
+    module.set("devMode", lua.create_function(|lua, ()| {
+        Ok(os(lua).dev_mode()?.unwrap_or(false))
+    })?)?;
+
+    lua.globals().set("sys", module)
+}
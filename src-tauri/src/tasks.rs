@@ -0,0 +1,249 @@
+use mlua::prelude::*;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use tokio::sync::watch;
+use tokio::task::AbortHandle;
+
+use crate::os_layer::os;
+use crate::output;
+use crate::simulate;
+
+#[derive(Clone)]
+enum Outcome {
+    Done(LuaMultiValue),
+    Failed(LuaError),
+    Cancelled,
+}
+
+/// A function running alongside the rest of the script, made with `task.spawn`.
+#[derive(Clone)]
+struct Task {
+    id: u64,
+    outcome: Arc<watch::Sender<Option<Outcome>>>,
+    abort: AbortHandle,
+}
+
+/// Every task a Lua state has started, so they can be stopped when its script ends.
+#[derive(Default)]
+struct Running(Mutex<Vec<Task>>);
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Only the first outcome counts, so a task cancelled as it finishes stays cancelled.
+fn settle(sender: &watch::Sender<Option<Outcome>>, outcome: Outcome) {
+    sender.send_if_modified(|current| {
+        if current.is_some() {
+            return false;
+        }
+
+        *current = Some(outcome);
+        true
+    });
+}
+
+impl Task {
+    fn cancel(&self) {
+        self.abort.abort();
+        settle(&self.outcome, Outcome::Cancelled);
+    }
+
+    async fn wait(&self) -> Outcome {
+        let mut receiver = self.outcome.subscribe();
+        let outcome = match receiver.wait_for(Option::is_some).await {
+            Ok(outcome) => outcome.clone().unwrap_or(Outcome::Cancelled),
+            Err(_) => Outcome::Cancelled,
+        };
+
+        outcome
+    }
+
+    fn status(&self) -> &'static str {
+        match &*self.outcome.borrow() {
+            None => "running",
+            Some(Outcome::Done(_)) => "done",
+            Some(Outcome::Failed(_)) => "failed",
+            Some(Outcome::Cancelled) => "cancelled",
+        }
+    }
+
+    /// What `join` gives back: the task's return values, nothing if it was cancelled, or its error.
+    fn results(&self, outcome: Outcome) -> LuaResult<LuaMultiValue> {
+        match outcome {
+            Outcome::Done(values) => Ok(values),
+            Outcome::Failed(e) => Err(LuaError::external(format!("Task {} failed: {e}", self.id))),
+            Outcome::Cancelled => Ok(LuaMultiValue::new()),
+        }
+    }
+}
+
+impl LuaUserData for Task {
+    fn add_methods<M: LuaUserDataMethods<Self>>(methods: &mut M) {
+        methods.add_async_method("join", |_, this, ()| async move {
+            let outcome = this.wait().await;
+            this.results(outcome)
+        });
+        methods.add_method("cancel", |_, this, ()| {
+            this.cancel();
+            Ok(())
+        });
+        methods.add_method("status", |_, this, ()| Ok(this.status()));
+        methods.add_meta_method(LuaMetaMethod::ToString, |_, this, ()| Ok(format!("task {} ({})", this.id, this.status())));
+        methods.add_meta_method(LuaMetaMethod::Eq, |_, this, other: LuaAnyUserData| {
+            Ok(other.borrow::<Task>().is_ok_and(|other| other.id == this.id))
+        });
+    }
+}
+
+fn spawn(lua: &Lua, function: LuaFunction, args: LuaMultiValue) -> LuaResult<Task> {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let (sender, _) = watch::channel(None);
+    let outcome = Arc::new(sender);
+
+    let running = function.call_async::<LuaMultiValue>(args);
+    let finished = outcome.clone();
+    let os = os(lua);
+
+    let handle = tokio::spawn(async move {
+        let outcome = match running.await {
+            Ok(values) => Outcome::Done(values),
+            // `exit()` only ends the task it's called in.
+            Err(e) if simulate::exit_code(&e).is_some() => Outcome::Done(LuaMultiValue::new()),
+            Err(e) => {
+                os.log(&format!("Task {id} failed: {e}"), output::LogLevel::Error, false);
+                Outcome::Failed(e)
+            }
+        };
+
+        settle(&finished, outcome);
+    });
+
+    let task = Task { id, outcome, abort: handle.abort_handle() };
+
+    if lua.app_data_ref::<Running>().is_none() {
+        lua.set_app_data(Running::default());
+    }
+    if let Some(running) = lua.app_data_ref::<Running>() {
+        let mut tasks = running.0.lock().unwrap();
+
+        // Finished ones have nothing left to cancel, and would keep their results alive until the script ends.
+        tasks.retain(|task| task.outcome.borrow().is_none());
+        tasks.push(task.clone());
+    }
+
+    Ok(task)
+}
+
+fn tasks_from(values: LuaMultiValue) -> LuaResult<Vec<Task>> {
+    values.into_iter()
+        .map(|value| match value {
+            LuaValue::UserData(data) => Ok(data.borrow::<Task>()?.clone()),
+            other => Err(LuaError::external(format!("expected a task from task.spawn, got {}", other.type_name()))),
+        })
+        .collect()
+}
+
+/// Waits for whichever task finishes first and gives back its index.
+async fn first_finished(tasks: &[Task]) -> usize {
+    let mut waits: Vec<Pin<Box<dyn Future<Output = usize> + Send + '_>>> = tasks.iter()
+        .enumerate()
+        .map(|(i, task)| Box::pin(async move {
+            task.wait().await;
+            i
+        }) as Pin<Box<dyn Future<Output = usize> + Send + '_>>)
+        .collect();
+
+    std::future::poll_fn(|cx| {
+        for wait in waits.iter_mut() {
+            if let Poll::Ready(i) = wait.as_mut().poll(cx) {
+                return Poll::Ready(i);
+            }
+        }
+
+        Poll::Pending
+    }).await
+}
+
+/// Stops every task `lua` started. Called when a script ends, since nothing can join them after that.
+pub(crate) fn cancel_all(lua: &Lua) {
+    if let Some(running) = lua.app_data_ref::<Running>() {
+        for task in running.0.lock().unwrap().drain(..) {
+            task.cancel();
+        }
+    }
+}
+
+/// Builds the `task` table.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    let module = lua.create_table()?;
+
+    module.set("spawn", lua.create_function(|lua, (function, args): (LuaFunction, LuaMultiValue)| {
+        spawn(lua, function, args)
+    })?)?;
+
+    // One task gives back everything it returned, several give back the first value of each.
+    module.set("join", lua.create_async_function(|_, tasks: LuaMultiValue| async move {
+        let tasks = tasks_from(tasks)?;
+
+        if let [task] = tasks.as_slice() {
+            let outcome = task.wait().await;
+            return task.results(outcome);
+        }
+
+        let mut firsts = LuaMultiValue::new();
+        for task in &tasks {
+            let outcome = task.wait().await;
+            firsts.push_back(task.results(outcome)?.pop_front().unwrap_or(LuaValue::Nil));
+        }
+
+        Ok(firsts)
+    })?)?;
+
+    // Gives back the task that finished first, then what it returned. The others keep running.
+    module.set("race", lua.create_async_function(|lua, tasks: LuaMultiValue| async move {
+        let tasks = tasks_from(tasks)?;
+
+        if tasks.is_empty() {
+            return Err(LuaError::external("task.race needs at least one task"));
+        }
+
+        let winner = tasks[first_finished(&tasks).await].clone();
+        let outcome = winner.wait().await;
+        let mut results = winner.results(outcome)?;
+
+        results.push_front(LuaValue::UserData(lua.create_userdata(winner)?));
+        Ok(results)
+    })?)?;
+
+    module.set("cancel", lua.create_function(|_, tasks: LuaMultiValue| {
+        for task in tasks_from(tasks)? {
+            task.cancel();
+        }
+
+        Ok(())
+    })?)?;
+
+    lua.globals().set("task", module)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lua_utils::get_custom_lua;
+
+    #[tokio::test]
+    async fn finished_tasks_are_forgotten() {
+        let lua = get_custom_lua();
+
+        lua.load(r#"
+            for i = 1, 100 do
+                task.join(task.spawn(function() return i end))
+            end
+            task.spawn(function() end)
+        "#).exec_async().await.unwrap();
+
+        assert_eq!(lua.app_data_ref::<Running>().unwrap().0.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,304 @@
+use serde::{Serialize, Deserialize};
+use std::collections::HashMap;
+use std::fs;
+
+use crate::files::{self, get_templates_dir};
+use crate::lua_utils::lua_quote;
+use crate::output;
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum ParamKind {
+    #[default]
+    Text,
+    Number,
+    List,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct TemplateParam {
+    pub(crate) name: String,
+    pub(crate) label: String,
+    #[serde(default)]
+    pub(crate) default: String,
+    #[serde(default)]
+    pub(crate) kind: ParamKind,
+}
+
+/// A script with `{{param}}` placeholders. Placeholders are replaced by Lua literals,
+/// so the body writes `openApp({{exe}})` rather than `openApp("{{exe}}")`.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub(crate) struct Template {
+    pub(crate) id: String,
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) description: String,
+    #[serde(default)]
+    pub(crate) params: Vec<TemplateParam>,
+    pub(crate) body: String,
+    #[serde(default)]
+    pub(crate) builtin: bool,
+}
+
+fn param(name: &str, label: &str, default: &str, kind: ParamKind) -> TemplateParam {
+    TemplateParam {
+        name: name.to_owned(),
+        label: label.to_owned(),
+        default: default.to_owned(),
+        kind,
+    }
+}
+
+fn builtin_templates() -> Vec<Template> {
+    vec![
+        Template {
+            id: "launch-and-wait".to_owned(),
+            name: "Launch and wait for close".to_owned(),
+            description: "Opens an executable, waits for its process to start and then waits until it closes.".to_owned(),
+            params: vec![
+                param("exe", "Path to executable", "", ParamKind::Text),
+                param("process", "Process name", "", ParamKind::Text),
+                param("timeout", "Start timeout (ms)", "60000", ParamKind::Number),
+            ],
+            body: r#"openApp({{exe}})
+
+if waitUntilAppOpen({{process}}, {{timeout}}) then
+    waitUntilAppClose({{process}})
+else
+    log("Timed out waiting for " .. {{process}} .. " to open", Warning, false)
+end
+"#.to_owned(),
+            builtin: true,
+        },
+        Template {
+            id: "url-then-window".to_owned(),
+            name: "Open URL and wait for window".to_owned(),
+            description: "Opens a URL (or protocol link) and waits for a window with the given title.".to_owned(),
+            params: vec![
+                param("url", "URL", "", ParamKind::Text),
+                param("window", "Window title", "", ParamKind::Text),
+                param("timeout", "Timeout (ms)", "60000", ParamKind::Number),
+            ],
+            body: r#"openURL({{url}})
+
+if not waitUntilWindowOpen({{window}}, {{timeout}}) then
+    log("Window " .. {{window}} .. " never opened", Warning, false)
+end
+"#.to_owned(),
+            builtin: true,
+        },
+        Template {
+            id: "steam".to_owned(),
+            name: "Launch via Steam".to_owned(),
+            description: "Starts a Steam game by its app ID and waits for the game's process to close.".to_owned(),
+            params: vec![
+                param("appid", "Steam app ID", "", ParamKind::Text),
+                param("process", "Game process name", "", ParamKind::Text),
+                param("timeout", "Start timeout (ms)", "120000", ParamKind::Number),
+            ],
+            body: r#"openURL("steam://rungameid/" .. {{appid}})
+
+if waitUntilAppOpen({{process}}, {{timeout}}) then
+    waitUntilAppClose({{process}})
+end
+"#.to_owned(),
+            builtin: true,
+        },
+        Template {
+            id: "chain".to_owned(),
+            name: "Chain apps".to_owned(),
+            description: "Opens several apps one after another with a delay between each.".to_owned(),
+            params: vec![
+                param("apps", "Apps (comma separated)", "", ParamKind::List),
+                param("delay", "Delay between apps (ms)", "2000", ParamKind::Number),
+            ],
+            body: r#"for _, app in ipairs({{apps}}) do
+    openApp(app)
+    sleep({{delay}})
+end
+"#.to_owned(),
+            builtin: true,
+        },
+    ]
+}
+
+fn user_templates() -> Vec<Template> {
+    let Some(dir) = get_templates_dir() else { return Vec::new() };
+    let Ok(entries) = fs::read_dir(&dir) else { return Vec::new() };
+
+    let mut templates = Vec::new();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+
+        if path.extension().and_then(|e| e.to_str()) != Some("json") {
+            continue;
+        }
+
+        match serde_json::from_str::<Template>(&files::get_file_content(path.to_string_lossy().to_string())) {
+            Ok(mut template) => {
+                template.builtin = false;
+                templates.push(template);
+            }
+            Err(e) => {
+                output::add_log(format!("[Loading Templates] Failed to parse {}: {e}", path.display()), output::LogLevel::Warning, false);
+            }
+        }
+    }
+
+    templates.sort_by_key(|t| t.name.to_lowercase());
+    templates
+}
+
+pub(crate) fn list_templates() -> Vec<Template> {
+    let mut templates = builtin_templates();
+    templates.extend(user_templates());
+    templates
+}
+
+pub(crate) fn find_template(id: &str) -> Option<Template> {
+    list_templates().into_iter().find(|t| t.id == id)
+}
+
+fn valid_id(id: &str) -> bool {
+    !id.is_empty() && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+pub(crate) fn save_template(mut template: Template) -> Result<(), String> {
+    if !valid_id(&template.id) {
+        return Err(format!("Invalid template id '{}'. Use letters, numbers, '-' and '_'.", template.id));
+    }
+
+    if builtin_templates().iter().any(|t| t.id == template.id) {
+        return Err(format!("'{}' is a built-in template", template.id));
+    }
+
+    let dir = get_templates_dir().ok_or("Failed to get templates directory")?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+
+    template.builtin = false;
+    let json = serde_json::to_string_pretty(&template).map_err(|e| e.to_string())?;
+
+    fs::write(dir.join(format!("{}.json", template.id)), json).map_err(|e| e.to_string())
+}
+
+pub(crate) fn delete_template(id: &str) -> Result<(), String> {
+    if !valid_id(id) {
+        return Err(format!("Invalid template id '{id}'"));
+    }
+
+    let dir = get_templates_dir().ok_or("Failed to get templates directory")?;
+
+    files::delete_file(dir.join(format!("{id}.json"))).map_err(|e| e.to_string())
+}
+
+fn literal(param: &TemplateParam, value: &str) -> Result<String, String> {
+    match param.kind {
+        ParamKind::Text => Ok(lua_quote(value)),
+        ParamKind::Number => {
+            let number: f64 = value.trim().parse()
+                .ok()
+                .filter(|n: &f64| n.is_finite())
+                .ok_or_else(|| format!("'{}' must be a number, got '{value}'", param.label))?;
+
+            if number.fract() == 0.0 && number.abs() < 1e15 {
+                Ok(format!("{}", number as i64))
+            } else {
+                Ok(number.to_string())
+            }
+        }
+        ParamKind::List => {
+            let items: Vec<String> = value.split(',')
+                .map(str::trim)
+                .filter(|item| !item.is_empty())
+                .map(lua_quote)
+                .collect();
+
+            Ok(format!("{{{}}}", items.join(", ")))
+        }
+    }
+}
+
+/// Replaces every `{{param}}` in the body in one pass, so a value that contains a placeholder
+/// is never substituted again. Placeholders that aren't a parameter are left as they are.
+pub(crate) fn render(template: &Template, values: &HashMap<String, String>) -> Result<String, String> {
+    let mut literals = HashMap::new();
+
+    for param in &template.params {
+        let value = values.get(&param.name)
+            .filter(|v| !v.trim().is_empty())
+            .unwrap_or(&param.default);
+
+        if value.trim().is_empty() && param.kind != ParamKind::List {
+            return Err(format!("Missing value for '{}'", param.label));
+        }
+
+        literals.insert(param.name.as_str(), literal(param, value)?);
+    }
+
+    let mut body = String::with_capacity(template.body.len());
+    let mut rest = template.body.as_str();
+
+    while let Some(start) = rest.find("{{") {
+        body.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+
+        match after.find("}}").and_then(|end| literals.get(&after[..end]).map(|literal| (end, literal))) {
+            Some((end, literal)) => {
+                body.push_str(literal);
+                rest = &after[end + 2..];
+            }
+            None => {
+                body.push_str("{{");
+                rest = after;
+            }
+        }
+    }
+
+    body.push_str(rest);
+
+    Ok(format!("-- Generated from the \"{}\" template\n{}", template.name.replace('\n', " "), body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn template(body: &str) -> Template {
+        Template {
+            id: "test".to_owned(),
+            name: "Test".to_owned(),
+            description: String::new(),
+            params: vec![
+                param("exe", "Executable", "", ParamKind::Text),
+                param("args", "Arguments", "", ParamKind::List),
+                param("wait", "Wait", "5000", ParamKind::Number),
+            ],
+            body: body.to_owned(),
+            builtin: false,
+        }
+    }
+
+    fn values(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn placeholders_are_replaced_by_literals() {
+        let rendered = render(&template("openApp({{exe}}, {{args}})\nsleep({{wait}})\n{{unknown}}"), &values(&[("exe", "game.exe"), ("args", "-a, -b")])).unwrap();
+
+        assert!(rendered.ends_with("openApp(\"game.exe\", {\"-a\", \"-b\"})\nsleep(5000)\n{{unknown}}"), "{rendered}");
+    }
+
+    #[test]
+    fn values_are_not_substituted_again() {
+        let rendered = render(&template("openApp({{exe}}, {{args}})"), &values(&[("exe", "{{args}}"), ("args", "\") os.exit() (\"")])).unwrap();
+
+        assert!(rendered.ends_with(r#"openApp("{{args}}", {"\") os.exit() (\""})"#), "{rendered}");
+    }
+
+    #[test]
+    fn missing_values_are_errors() {
+        assert!(render(&template("openApp({{exe}})"), &values(&[])).is_err());
+        assert!(render(&template("sleep({{wait}})"), &values(&[("exe", "a"), ("wait", "soon")])).is_err());
+    }
+}
@@ -0,0 +1,174 @@
+use tauri::menu::{Menu, MenuBuilder, MenuEvent, SubmenuBuilder};
+use tauri::tray::{MouseButton, MouseButtonState, TrayIcon, TrayIconBuilder, TrayIconEvent};
+use tauri::{AppHandle, Emitter, Manager, Wry};
+
+use crate::files;
+use crate::lua_utils::{self, script_path};
+use crate::output;
+use crate::runs;
+
+const TRAY_ID: &str = "luauncher";
+const WINDOW: &str = "Luauncher";
+
+fn window_visible(app: &AppHandle) -> bool {
+    app.get_webview_window(WINDOW)
+        .and_then(|window| window.is_visible().ok())
+        .unwrap_or(false)
+}
+
+/// Whether the tray icon is there, so hiding the window doesn't lose it.
+pub(crate) fn is_shown(app: &AppHandle) -> bool {
+    app.tray_by_id(TRAY_ID).is_some()
+}
+
+pub(crate) fn show(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(WINDOW) {
+        let _ = window.unminimize();
+        let _ = window.show();
+        let _ = window.set_focus();
+    }
+
+    refresh();
+}
+
+pub(crate) fn hide(app: &AppHandle) {
+    if let Some(window) = app.get_webview_window(WINDOW) {
+        let _ = window.hide();
+    }
+
+    refresh();
+}
+
+/// Favourites first, then the recently played games that aren't favourites, the running scripts
+/// and the launcher itself. Games whose script is gone are left out.
+fn build_menu(app: &AppHandle) -> tauri::Result<Menu<Wry>> {
+    let settings = files::load_settings().ok().flatten().unwrap_or_default();
+
+    let favourites: Vec<&String> = settings.favourites.iter()
+        .filter(|game| script_path(game).is_file())
+        .collect();
+    let recent: Vec<&String> = settings.recent.iter()
+        .filter(|game| !settings.favourites.contains(game) && script_path(game).is_file())
+        .collect();
+    let running = runs::list();
+
+    let mut menu = MenuBuilder::new(app);
+
+    for game in &favourites {
+        menu = menu.text(format!("run:{game}"), game.as_str());
+    }
+
+    if !recent.is_empty() {
+        let mut submenu = SubmenuBuilder::new(app, "Recently Played");
+
+        for game in &recent {
+            submenu = submenu.text(format!("run:{game}"), game.as_str());
+        }
+
+        menu = menu.item(&submenu.build()?);
+    }
+
+    if !favourites.is_empty() || !recent.is_empty() {
+        menu = menu.separator();
+    }
+
+    for run in &running {
+        menu = menu.text(format!("cancel:{}", run.game), format!("Cancel {}", run.game));
+    }
+
+    if !running.is_empty() {
+        menu = menu.separator();
+    }
+
+    let toggle = if window_visible(app) { "Hide Luauncher" } else { "Show Luauncher" };
+
+    menu.text("toggle", toggle)
+        .text("settings", "Settings")
+        .separator()
+        .text("quit", "Quit")
+        .build()
+}
+
+/// Launches `game` like clicking it in the library does, including hiding the window when
+/// the `close` setting is on.
+fn launch(app: &AppHandle, game: String) {
+    if files::load_settings().ok().flatten().is_some_and(|settings| settings.close) {
+        hide(app);
+    }
+
+    tokio::spawn(async move {
+        if let Err(e) = lua_utils::lua_run_game(&game).await.map_err(|e| e.to_string()) {
+            output::add_log(format!("[Tray] Running {game} failed: {e}"), output::LogLevel::Error, false);
+        }
+    });
+}
+
+fn on_menu_event(app: &AppHandle, event: MenuEvent) {
+    let id = event.id().as_ref();
+
+    if let Some(game) = id.strip_prefix("run:") {
+        launch(app, game.to_owned());
+        return;
+    }
+
+    if let Some(game) = id.strip_prefix("cancel:") {
+        if runs::cancel(game) {
+            output::add_log(format!("[Tray] Cancelled {game}"), output::LogLevel::Info, false);
+        }
+        return;
+    }
+
+    match id {
+        "toggle" if window_visible(app) => hide(app),
+        "toggle" => show(app),
+        "settings" => {
+            show(app);
+
+            if let Err(e) = app.emit("open_settings", ()) {
+                output::add_log(format!("[Tray] Failed to open the settings: {e}"), output::LogLevel::Warning, true);
+            }
+        }
+        "quit" => app.exit(0),
+        _ => {}
+    }
+}
+
+/// Clicking the icon brings the window back, the menu is on right click.
+fn on_tray_event(tray: &TrayIcon, event: TrayIconEvent) {
+    if let TrayIconEvent::Click { button: MouseButton::Left, button_state: MouseButtonState::Up, .. } = event {
+        show(tray.app_handle());
+    }
+}
+
+pub(crate) fn create(app: &AppHandle) -> tauri::Result<()> {
+    let mut tray = TrayIconBuilder::with_id(TRAY_ID)
+        .tooltip("Luauncher")
+        .menu(&build_menu(app)?)
+        .show_menu_on_left_click(false)
+        .on_menu_event(on_menu_event)
+        .on_tray_icon_event(on_tray_event);
+
+    if let Some(icon) = app.default_window_icon() {
+        tray = tray.icon(icon.clone());
+    }
+
+    tray.build(app)?;
+
+    Ok(())
+}
+
+/// Rebuilds the menu, for when the favourites, recent games, running scripts or window change.
+pub(crate) fn refresh() {
+    let Some(app) = crate::app_handle() else {
+        return;
+    };
+    let Some(tray) = app.tray_by_id(TRAY_ID) else {
+        return;
+    };
+
+    let result = build_menu(app).and_then(|menu| tray.set_menu(Some(menu)));
+
+    if let Err(e) = result {
+        output::add_log(format!("[Tray] Failed to update the menu: {e}"), output::LogLevel::Warning, true);
+    }
+}
@@ -0,0 +1,653 @@
+use mlua::prelude::*;
+use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::matching::{MatchMode, MatchOptions, Matcher};
+use crate::os_layer::os;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(default)]
+pub(crate) struct WindowInfo {
+    pub(crate) id: u64,
+    pub(crate) title: String,
+    pub(crate) class: String,
+    pub(crate) pid: Option<u32>,
+    pub(crate) x: i32,
+    pub(crate) y: i32,
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+}
+
+#[derive(Debug)]
+pub(crate) enum WindowError {
+    /// Nothing on this system can be asked about windows.
+    NoBackend(String),
+    /// A backend exists but the query failed.
+    Backend(String),
+}
+
+impl fmt::Display for WindowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WindowError::NoBackend(reason) => write!(f, "No window backend available: {reason}"),
+            WindowError::Backend(reason) => write!(f, "Window query failed: {reason}"),
+        }
+    }
+}
+
+impl std::error::Error for WindowError {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum WindowAction {
+    Focus,
+    Minimize,
+    Maximize,
+    Move(i32, i32),
+    Resize(u32, u32),
+    Close,
+}
+
+impl WindowAction {
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            WindowAction::Focus => "focus",
+            WindowAction::Minimize => "minimize",
+            WindowAction::Maximize => "maximize",
+            WindowAction::Move(..) => "move",
+            WindowAction::Resize(..) => "resize",
+            WindowAction::Close => "close",
+        }
+    }
+}
+
+pub(crate) trait WindowBackend: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn list(&self) -> Result<Vec<WindowInfo>, WindowError>;
+    fn act(&self, window: &WindowInfo, action: WindowAction) -> Result<(), WindowError>;
+}
+
+/// The X server connection, kept between calls since `waitUntilWindowOpen` asks several times a
+/// second. It's dropped when a query fails, so the next one reconnects.
+#[cfg(target_os = "linux")]
+static X11: std::sync::Mutex<Option<Arc<x11::X11>>> = std::sync::Mutex::new(None);
+
+/// Picks the backend for the current session, or explains why there isn't one.
+pub(crate) fn backend() -> Result<Arc<dyn WindowBackend>, WindowError> {
+    #[cfg(target_os = "windows")]
+    {
+        Ok(Arc::new(win32::Win32))
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        Ok(Arc::new(macos::SystemEvents))
+    }
+
+    #[cfg(target_os = "linux")]
+    {
+        let has_x11 = std::env::var_os("DISPLAY").is_some_and(|d| !d.is_empty());
+        let has_wayland = std::env::var_os("WAYLAND_DISPLAY").is_some_and(|d| !d.is_empty());
+
+        if has_x11 {
+            let mut cached = X11.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+
+            if let Some(backend) = cached.as_ref() {
+                return Ok(backend.clone());
+            }
+
+            let backend = Arc::new(x11::X11::connect()?);
+
+            if has_wayland {
+                crate::output::add_log(
+                    "[Windows] This is a Wayland session, so only windows running through XWayland can be seen, native Wayland ones are missing".to_owned(),
+                    crate::output::LogLevel::Warning,
+                    false,
+                );
+            }
+
+            *cached = Some(backend.clone());
+            return Ok(backend);
+        }
+
+        if has_wayland {
+            return Err(WindowError::NoBackend("this is a Wayland session without XWayland, and Wayland doesn't let apps list other windows".to_owned()));
+        }
+
+        Err(WindowError::NoBackend("no display server found (DISPLAY and WAYLAND_DISPLAY are unset)".to_owned()))
+    }
+}
+
+/// Forgets the cached connection after a failed query, in case the X server went away.
+fn reset() {
+    #[cfg(target_os = "linux")]
+    {
+        *X11.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
+    }
+}
+
+pub(crate) fn list_windows() -> Result<Vec<WindowInfo>, WindowError> {
+    let backend = backend()?;
+
+    backend.list().inspect_err(|_| reset()).map_err(|e| match e {
+        WindowError::Backend(reason) => WindowError::Backend(format!("{} backend: {reason}", backend.name())),
+        other => other,
+    })
+}
+
+pub(crate) fn act(window: &WindowInfo, action: WindowAction) -> Result<(), WindowError> {
+    let backend = backend()?;
+
+    backend.act(window, action).inspect_err(|_| reset()).map_err(|e| match e {
+        WindowError::Backend(reason) => WindowError::Backend(format!("{} backend: {reason}", backend.name())),
+        other => other,
+    })
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct QueryOptions {
+    title: Option<String>,
+    class: Option<String>,
+    pid: Option<u32>,
+    #[serde(flatten)]
+    matching: MatchOptions,
+}
+
+/// What a script passed to `window.find` and friends: part of a title, or a table with `title`,
+/// `class` and `pid` plus the usual `match` and `ignoreCase` options for the title and class.
+pub(crate) struct WindowQuery {
+    title: Option<Matcher>,
+    class: Option<Matcher>,
+    pid: Option<u32>,
+    description: String,
+}
+
+impl WindowQuery {
+    fn from_lua(lua: &Lua, value: LuaValue) -> LuaResult<Self> {
+        let options = match value {
+            LuaValue::String(title) => QueryOptions { title: Some(title.to_str()?.to_owned()), ..Default::default() },
+            LuaValue::Table(_) => lua.from_value(value)?,
+            other => return Err(LuaError::external(format!("Expected a window title or a table, got {}", other.type_name()))),
+        };
+
+        let mode = options.matching.mode.unwrap_or(MatchMode::Substring);
+        let matcher = |text: &Option<String>| -> LuaResult<Option<Matcher>> {
+            text.as_deref()
+                .map(|text| Matcher::new(text, mode, options.matching.ignore_case).map_err(LuaError::external))
+                .transpose()
+        };
+
+        let mut description = Vec::new();
+        if let Some(title) = &options.title {
+            description.push(format!("title '{title}'"));
+        }
+        if let Some(class) = &options.class {
+            description.push(format!("class '{class}'"));
+        }
+        if let Some(pid) = options.pid {
+            description.push(format!("pid {pid}"));
+        }
+
+        Ok(WindowQuery {
+            title: matcher(&options.title)?,
+            class: matcher(&options.class)?,
+            pid: options.pid,
+            description: if description.is_empty() { "any window".to_owned() } else { description.join(", ") },
+        })
+    }
+
+    pub(crate) fn matches(&self, window: &WindowInfo) -> bool {
+        self.title.as_ref().is_none_or(|m| m.is_match(&window.title))
+            && self.class.as_ref().is_none_or(|m| m.is_match(&window.class))
+            && self.pid.is_none_or(|pid| window.pid == Some(pid))
+    }
+}
+
+/// A window table from `window.list`/`window.find` is used as is, anything else is treated as a query.
+fn resolve(lua: &Lua, target: LuaValue) -> LuaResult<WindowInfo> {
+    if let LuaValue::Table(table) = &target {
+        if table.contains_key("id")? {
+            return lua.from_value(target);
+        }
+    }
+
+    let query = WindowQuery::from_lua(lua, target)?;
+
+    os(lua).list_windows()?
+        .into_iter()
+        .find(|w| query.matches(w))
+        .ok_or_else(|| LuaError::external(format!("No window matches {}", query.description)))
+}
+
+fn register_action(lua: &Lua, module: &LuaTable, name: &str, action: WindowAction) -> LuaResult<()> {
+    module.set(name, lua.create_function(move |lua, target: LuaValue| {
+        let window = resolve(lua, target)?;
+        os(lua).window_action(&window, action)
+    })?)
+}
+
+/// Builds the `window` table scripts use to find and arrange windows.
+pub(crate) fn register(lua: &Lua) -> LuaResult<()> {
+    let module = lua.create_table()?;
+
+    module.set("list", lua.create_function(|lua, ()| {
+        lua.to_value(&os(lua).list_windows()?)
+    })?)?;
+
+    module.set("find", lua.create_function(|lua, query: LuaValue| {
+        let query = WindowQuery::from_lua(lua, query)?;
+
+        match os(lua).list_windows()?.into_iter().find(|w| query.matches(w)) {
+            Some(window) => lua.to_value(&window),
+            None => Ok(LuaValue::Nil),
+        }
+    })?)?;
+
+    module.set("findAll", lua.create_function(|lua, query: LuaValue| {
+        let query = WindowQuery::from_lua(lua, query)?;
+        let windows: Vec<WindowInfo> = os(lua).list_windows()?.into_iter().filter(|w| query.matches(w)).collect();
+
+        lua.to_value(&windows)
+    })?)?;
+
+    register_action(lua, &module, "focus", WindowAction::Focus)?;
+    register_action(lua, &module, "minimize", WindowAction::Minimize)?;
+    register_action(lua, &module, "maximize", WindowAction::Maximize)?;
+    register_action(lua, &module, "close", WindowAction::Close)?;
+
+    module.set("move", lua.create_function(|lua, (target, x, y): (LuaValue, i32, i32)| {
+        let window = resolve(lua, target)?;
+        os(lua).window_action(&window, WindowAction::Move(x, y))
+    })?)?;
+
+    module.set("resize", lua.create_function(|lua, (target, width, height): (LuaValue, u32, u32)| {
+        let window = resolve(lua, target)?;
+        os(lua).window_action(&window, WindowAction::Resize(width, height))
+    })?)?;
+
+    lua.globals().set("window", module)
+}
+
+#[cfg(target_os = "linux")]
+mod x11 {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xproto::{Atom, AtomEnum, ClientMessageEvent, ConnectionExt, EventMask, Window};
+    use x11rb::rust_connection::RustConnection;
+    use x11rb::CURRENT_TIME;
+
+    use super::{WindowAction, WindowBackend, WindowError, WindowInfo};
+
+    /// Tells the window manager the request comes from a pager/taskbar, which it trusts more than an app.
+    const SOURCE_PAGER: u32 = 2;
+    const ICONIC_STATE: u32 = 3;
+    const NET_WM_STATE_ADD: u32 = 1;
+
+    fn err(e: impl std::fmt::Display) -> WindowError {
+        WindowError::Backend(e.to_string())
+    }
+
+    struct Atoms {
+        net_client_list: Atom,
+        net_wm_name: Atom,
+        net_wm_pid: Atom,
+        utf8_string: Atom,
+        net_active_window: Atom,
+        wm_change_state: Atom,
+        net_wm_state: Atom,
+        net_wm_state_maximized_vert: Atom,
+        net_wm_state_maximized_horz: Atom,
+        net_moveresize_window: Atom,
+        net_close_window: Atom,
+    }
+
+    /// Talks to the X server directly and reads the EWMH properties the window manager keeps on the root window.
+    pub(crate) struct X11 {
+        conn: RustConnection,
+        root: Window,
+        atoms: Atoms,
+    }
+
+    impl X11 {
+        pub(crate) fn connect() -> Result<Self, WindowError> {
+            let (conn, screen) = x11rb::connect(None)
+                .map_err(|e| WindowError::NoBackend(format!("couldn't connect to the X server: {e}")))?;
+            let root = conn.setup().roots[screen].root;
+
+            let intern = |name: &[u8]| -> Result<Atom, WindowError> {
+                Ok(conn.intern_atom(false, name).map_err(err)?.reply().map_err(err)?.atom)
+            };
+
+            let atoms = Atoms {
+                net_client_list: intern(b"_NET_CLIENT_LIST")?,
+                net_wm_name: intern(b"_NET_WM_NAME")?,
+                net_wm_pid: intern(b"_NET_WM_PID")?,
+                utf8_string: intern(b"UTF8_STRING")?,
+                net_active_window: intern(b"_NET_ACTIVE_WINDOW")?,
+                wm_change_state: intern(b"WM_CHANGE_STATE")?,
+                net_wm_state: intern(b"_NET_WM_STATE")?,
+                net_wm_state_maximized_vert: intern(b"_NET_WM_STATE_MAXIMIZED_VERT")?,
+                net_wm_state_maximized_horz: intern(b"_NET_WM_STATE_MAXIMIZED_HORZ")?,
+                net_moveresize_window: intern(b"_NET_MOVERESIZE_WINDOW")?,
+                net_close_window: intern(b"_NET_CLOSE_WINDOW")?,
+            };
+
+            Ok(X11 { conn, root, atoms })
+        }
+
+        /// Sends an EWMH client message to the root window, which is how windows of other apps are managed.
+        fn send(&self, window: Window, message: Atom, data: [u32; 5]) -> Result<(), WindowError> {
+            let event = ClientMessageEvent::new(32, window, message, data);
+
+            self.conn.send_event(false, self.root, EventMask::SUBSTRUCTURE_REDIRECT | EventMask::SUBSTRUCTURE_NOTIFY, event)
+                .map_err(err)?
+                .check()
+                .map_err(err)
+        }
+
+        fn property(&self, window: Window, property: Atom, kind: impl Into<Atom>) -> Result<x11rb::protocol::xproto::GetPropertyReply, WindowError> {
+            self.conn.get_property(false, window, property, kind, 0, u32::MAX / 4)
+                .map_err(err)?
+                .reply()
+                .map_err(err)
+        }
+
+        fn title(&self, window: Window) -> String {
+            if let Ok(reply) = self.property(window, self.atoms.net_wm_name, self.atoms.utf8_string) {
+                if !reply.value.is_empty() {
+                    return String::from_utf8_lossy(&reply.value).to_string();
+                }
+            }
+
+            self.property(window, AtomEnum::WM_NAME.into(), AtomEnum::ANY)
+                .map(|reply| String::from_utf8_lossy(&reply.value).to_string())
+                .unwrap_or_default()
+        }
+
+        fn class(&self, window: Window) -> String {
+            self.property(window, AtomEnum::WM_CLASS.into(), AtomEnum::STRING)
+                .ok()
+                .and_then(|reply| {
+                    reply.value.split(|b| *b == 0)
+                        .filter(|part| !part.is_empty())
+                        .nth(1)
+                        .map(|class| String::from_utf8_lossy(class).to_string())
+                })
+                .unwrap_or_default()
+        }
+
+        fn pid(&self, window: Window) -> Option<u32> {
+            self.property(window, self.atoms.net_wm_pid, AtomEnum::CARDINAL)
+                .ok()?
+                .value32()?
+                .next()
+        }
+
+        fn info(&self, window: Window) -> WindowInfo {
+            let mut info = WindowInfo {
+                id: window as u64,
+                title: self.title(window),
+                class: self.class(window),
+                pid: self.pid(window),
+                ..Default::default()
+            };
+
+            if let Ok(Ok(geometry)) = self.conn.get_geometry(window).map(|c| c.reply()) {
+                info.width = geometry.width as u32;
+                info.height = geometry.height as u32;
+            }
+
+            if let Ok(Ok(position)) = self.conn.translate_coordinates(window, self.root, 0, 0).map(|c| c.reply()) {
+                info.x = position.dst_x as i32;
+                info.y = position.dst_y as i32;
+            }
+
+            info
+        }
+    }
+
+    impl WindowBackend for X11 {
+        fn name(&self) -> &'static str {
+            "X11"
+        }
+
+        fn list(&self) -> Result<Vec<WindowInfo>, WindowError> {
+            let reply = self.property(self.root, self.atoms.net_client_list, AtomEnum::WINDOW)?;
+
+            if reply.type_ == u32::from(AtomEnum::NONE) {
+                return Err(WindowError::NoBackend("the window manager doesn't publish _NET_CLIENT_LIST (it isn't EWMH compliant)".to_owned()));
+            }
+
+            let windows: Vec<Window> = reply.value32()
+                .ok_or_else(|| WindowError::Backend("_NET_CLIENT_LIST has an unexpected format".to_owned()))?
+                .collect();
+
+            Ok(windows.into_iter().map(|w| self.info(w)).collect())
+        }
+
+        fn act(&self, window: &WindowInfo, action: WindowAction) -> Result<(), WindowError> {
+            let id = window.id as Window;
+            let atoms = &self.atoms;
+
+            match action {
+                WindowAction::Focus => self.send(id, atoms.net_active_window, [SOURCE_PAGER, CURRENT_TIME, 0, 0, 0]),
+                WindowAction::Minimize => self.send(id, atoms.wm_change_state, [ICONIC_STATE, 0, 0, 0, 0]),
+                WindowAction::Maximize => {
+                    let (vertical, horizontal) = (atoms.net_wm_state_maximized_vert, atoms.net_wm_state_maximized_horz);
+
+                    self.send(id, atoms.net_wm_state, [NET_WM_STATE_ADD, vertical, horizontal, SOURCE_PAGER, 0])
+                }
+                // Bits 8 to 11 say which of x, y, width and height are set, bits 12 and 13 are the source.
+                WindowAction::Move(x, y) => {
+                    self.send(id, atoms.net_moveresize_window, [(1 << 8) | (1 << 9) | (SOURCE_PAGER << 12), x as u32, y as u32, 0, 0])
+                }
+                WindowAction::Resize(width, height) => {
+                    self.send(id, atoms.net_moveresize_window, [(1 << 10) | (1 << 11) | (SOURCE_PAGER << 12), 0, 0, width, height])
+                }
+                WindowAction::Close => self.send(id, atoms.net_close_window, [CURRENT_TIME, SOURCE_PAGER, 0, 0, 0]),
+            }
+        }
+    }
+}
+
+#[cfg(target_os = "windows")]
+mod win32 {
+    use winapi::shared::minwindef::{BOOL, LPARAM, TRUE};
+    use winapi::shared::windef::{HWND, RECT};
+    use winapi::um::winuser::{
+        EnumWindows, GetClassNameW, GetWindowRect, GetWindowTextLengthW, GetWindowTextW, GetWindowThreadProcessId, IsIconic,
+        IsWindow, IsWindowVisible, PostMessageW, SetForegroundWindow, SetWindowPos, ShowWindow, SWP_NOACTIVATE, SWP_NOMOVE,
+        SWP_NOSIZE, SWP_NOZORDER, SW_MAXIMIZE, SW_MINIMIZE, SW_RESTORE, WM_CLOSE,
+    };
+
+    use super::{WindowAction, WindowBackend, WindowError, WindowInfo};
+
+    pub(crate) struct Win32;
+
+    unsafe extern "system" fn collect(hwnd: HWND, lparam: LPARAM) -> BOOL {
+        let windows = &mut *(lparam as *mut Vec<HWND>);
+        windows.push(hwnd);
+        TRUE
+    }
+
+    fn info(hwnd: HWND) -> WindowInfo {
+        unsafe {
+            let length = GetWindowTextLengthW(hwnd);
+            let mut title = vec![0u16; length as usize + 1];
+            let copied = GetWindowTextW(hwnd, title.as_mut_ptr(), title.len() as i32);
+
+            let mut class = vec![0u16; 256];
+            let class_len = GetClassNameW(hwnd, class.as_mut_ptr(), class.len() as i32);
+
+            let mut pid = 0u32;
+            GetWindowThreadProcessId(hwnd, &mut pid);
+
+            let mut rect: RECT = std::mem::zeroed();
+            GetWindowRect(hwnd, &mut rect);
+
+            WindowInfo {
+                id: hwnd as usize as u64,
+                title: String::from_utf16_lossy(&title[..copied.max(0) as usize]),
+                class: String::from_utf16_lossy(&class[..class_len.max(0) as usize]),
+                pid: (pid != 0).then_some(pid),
+                x: rect.left,
+                y: rect.top,
+                width: (rect.right - rect.left).max(0) as u32,
+                height: (rect.bottom - rect.top).max(0) as u32,
+            }
+        }
+    }
+
+    impl WindowBackend for Win32 {
+        fn name(&self) -> &'static str {
+            "Win32"
+        }
+
+        fn list(&self) -> Result<Vec<WindowInfo>, WindowError> {
+            let mut handles: Vec<HWND> = Vec::new();
+
+            let ok = unsafe { EnumWindows(Some(collect), &mut handles as *mut Vec<HWND> as LPARAM) };
+            if ok == 0 {
+                return Err(WindowError::Backend(std::io::Error::last_os_error().to_string()));
+            }
+
+            Ok(handles.into_iter()
+                .filter(|&hwnd| unsafe { IsWindowVisible(hwnd) } != 0)
+                .map(info)
+                .filter(|w| !w.title.is_empty())
+                .collect())
+        }
+
+        fn act(&self, window: &WindowInfo, action: WindowAction) -> Result<(), WindowError> {
+            let hwnd = window.id as usize as HWND;
+
+            if unsafe { IsWindow(hwnd) } == 0 {
+                return Err(WindowError::Backend(format!("'{}' is no longer open", window.title)));
+            }
+
+            let ok = unsafe {
+                match action {
+                    WindowAction::Focus => {
+                        if IsIconic(hwnd) != 0 {
+                            ShowWindow(hwnd, SW_RESTORE);
+                        }
+                        SetForegroundWindow(hwnd)
+                    }
+                    // ShowWindow returns whether the window was visible before, not whether it worked.
+                    WindowAction::Minimize => {
+                        ShowWindow(hwnd, SW_MINIMIZE);
+                        TRUE
+                    }
+                    WindowAction::Maximize => {
+                        ShowWindow(hwnd, SW_MAXIMIZE);
+                        TRUE
+                    }
+                    WindowAction::Move(x, y) => SetWindowPos(hwnd, std::ptr::null_mut(), x, y, 0, 0, SWP_NOSIZE | SWP_NOZORDER | SWP_NOACTIVATE),
+                    WindowAction::Resize(width, height) => {
+                        SetWindowPos(hwnd, std::ptr::null_mut(), 0, 0, width as i32, height as i32, SWP_NOMOVE | SWP_NOZORDER | SWP_NOACTIVATE)
+                    }
+                    WindowAction::Close => PostMessageW(hwnd, WM_CLOSE, 0, 0),
+                }
+            };
+
+            if ok == 0 {
+                return Err(WindowError::Backend(format!("couldn't {} '{}': {}", action.name(), window.title, std::io::Error::last_os_error())));
+            }
+
+            Ok(())
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+mod macos {
+    use std::process::Command;
+
+    use super::{WindowAction, WindowBackend, WindowError, WindowInfo};
+
+    fn quote(text: &str) -> String {
+        format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+
+    fn run(script: &str) -> Result<String, WindowError> {
+        let output = Command::new("osascript")
+            .args(["-e", script])
+            .output()
+            .map_err(|e| WindowError::NoBackend(format!("couldn't run osascript: {e}")))?;
+
+        if !output.status.success() {
+            return Err(WindowError::Backend(String::from_utf8_lossy(&output.stderr).trim().to_owned()));
+        }
+
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    const SCRIPT: &str = r#"set out to ""
+tell application "System Events"
+    repeat with p in (every process whose background only is false)
+        set pname to name of p
+        set ppid to unix id of p
+        repeat with w in (every window of p)
+            set {x, y} to position of w
+            set {wd, ht} to size of w
+            set out to out & pname & tab & (name of w) & tab & ppid & tab & x & tab & y & tab & wd & tab & ht & linefeed
+        end repeat
+    end repeat
+end tell
+return out"#;
+
+    /// Asks System Events through AppleScript, which needs the accessibility permission.
+    pub(crate) struct SystemEvents;
+
+    impl WindowBackend for SystemEvents {
+        fn name(&self) -> &'static str {
+            "System Events"
+        }
+
+        fn list(&self) -> Result<Vec<WindowInfo>, WindowError> {
+            let stdout = run(SCRIPT)?;
+
+            Ok(stdout.lines()
+                .enumerate()
+                .filter_map(|(i, line)| {
+                    let parts: Vec<&str> = line.split('\t').collect();
+                    if parts.len() < 7 {
+                        return None;
+                    }
+
+                    Some(WindowInfo {
+                        id: i as u64,
+                        class: parts[0].to_owned(),
+                        title: parts[1].to_owned(),
+                        pid: parts[2].trim().parse().ok(),
+                        x: parts[3].trim().parse().unwrap_or(0),
+                        y: parts[4].trim().parse().unwrap_or(0),
+                        width: parts[5].trim().parse().unwrap_or(0),
+                        height: parts[6].trim().parse().unwrap_or(0),
+                    })
+                })
+                .collect())
+        }
+
+        /// Windows are found again by process and title, since System Events has no stable window ids.
+        fn act(&self, window: &WindowInfo, action: WindowAction) -> Result<(), WindowError> {
+            let pid = window.pid.ok_or_else(|| WindowError::Backend(format!("'{}' has no process id", window.title)))?;
+
+            let command = match action {
+                WindowAction::Focus => "set frontmost to true\nperform action \"AXRaise\" of w".to_owned(),
+                WindowAction::Minimize => "set value of attribute \"AXMinimized\" of w to true".to_owned(),
+                WindowAction::Maximize => "click (first button of w whose subrole is \"AXZoomButton\")".to_owned(),
+                WindowAction::Move(x, y) => format!("set position of w to {{{x}, {y}}}"),
+                WindowAction::Resize(width, height) => format!("set size of w to {{{width}, {height}}}"),
+                WindowAction::Close => "click (first button of w whose subrole is \"AXCloseButton\")".to_owned(),
+            };
+
+            run(&format!(
+                "tell application \"System Events\"\ntell (first process whose unix id is {pid})\nset w to first window whose name is {}\n{command}\nend tell\nend tell",
+                quote(&window.title),
+            ))?;
+
+            Ok(())
+        }
+    }
+}